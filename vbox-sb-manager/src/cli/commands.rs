@@ -1,11 +1,68 @@
 //! CLI command implementations
 
-use crate::config::Config;
-use crate::error::Result;
-use crate::modules::{kvm, mok, signing, verification};
+use crate::cli::status;
+use crate::config::{Config, SystemPaths};
+use crate::error::{Result, VBoxError};
+use crate::modules::target::ModuleTarget;
+use crate::modules::{hook, kvm, mok, service, signing, verification};
+use crate::utils::logfile;
+use crate::utils::output::OutputFormat;
 use crate::utils::{output, system};
 use dialoguer::{Password, Input, Confirm};
 
+/// Resolves a `--target` argument to the list of targets a command should
+/// operate on: `config.default_target` when unset, a single named DKMS
+/// package, or every DKMS-managed package for `--target all`.
+pub fn resolve_targets(target_arg: Option<&str>, config: &Config) -> Result<Vec<ModuleTarget>> {
+    match target_arg {
+        None => ModuleTarget::find(&config.default_target, config).map(|target| vec![target]),
+        Some("all") => ModuleTarget::autodetect_all(config),
+        Some(name) => ModuleTarget::find(name, config).map(|target| vec![target]),
+    }
+}
+
+/// Resolves the `--kernel <ver>` / `--all-kernels` pair on `sign`, `verify`,
+/// and `full` to the set of kernel versions a command should operate on.
+/// `Running` (the default) preserves every pre-existing single-kernel
+/// behavior - including `auto_reload_after_sign` - so picking neither flag
+/// changes nothing.
+#[derive(Debug, Clone)]
+pub enum KernelSelector {
+    /// The currently running kernel (`uname -r`) - the default
+    Running,
+    /// One explicitly named kernel, e.g. a just-installed kernel not yet
+    /// booted into
+    Specific(String),
+    /// Every kernel under `/lib/modules`
+    All,
+}
+
+impl KernelSelector {
+    pub fn from_args(kernel: Option<&str>, all_kernels: bool) -> Self {
+        match (kernel, all_kernels) {
+            (_, true) => KernelSelector::All,
+            (Some(version), false) => KernelSelector::Specific(version.to_string()),
+            (None, false) => KernelSelector::Running,
+        }
+    }
+
+    pub fn resolve(&self) -> Result<Vec<String>> {
+        match self {
+            KernelSelector::Running => Ok(vec![SystemPaths::kernel_version()?]),
+            KernelSelector::Specific(version) => Ok(vec![version.clone()]),
+            KernelSelector::All => SystemPaths::all_kernel_versions(),
+        }
+    }
+
+    /// True when this selector resolves to exactly the running kernel,
+    /// which gates behavior (like `auto_reload_after_sign`) that only makes
+    /// sense for modules the running kernel will actually load.
+    fn is_running_kernel_only(&self) -> Result<bool> {
+        Ok(matches!(self, KernelSelector::Running)
+            || matches!(self, KernelSelector::Specific(version) if *version == SystemPaths::kernel_version()?))
+    }
+}
+
 /// Setup command: Create signing keys and enroll MOK
 pub fn setup_command(config: &Config) -> Result<()> {
     system::check_root()?;
@@ -76,71 +133,216 @@ pub fn setup_command(config: &Config) -> Result<()> {
     Ok(())
 }
 
-/// Sign command: Sign VirtualBox modules
-pub fn sign_command(config: &Config) -> Result<()> {
+/// Resolves the signing-key passphrase: from `config.passphrase_file` when
+/// set, so the kernel-upgrade hook (see [`install_hook_command`]) can sign
+/// modules with no terminal to prompt on, or by prompting interactively
+/// otherwise. The file must be root-owned and mode 0600 - the same
+/// restriction already enforced on the signing keys themselves - so the
+/// passphrase is never left world-readable, and the hook never has to hand
+/// it over in an environment variable that could end up in a log.
+fn resolve_passphrase(config: &Config) -> Result<String> {
+    match &config.passphrase_file {
+        Some(path) => read_passphrase_file(path),
+        None => Password::new()
+            .with_prompt("Enter passphrase for signing key")
+            .interact()
+            .map_err(Into::into),
+    }
+}
+
+#[cfg(unix)]
+fn read_passphrase_file(path: &std::path::Path) -> Result<String> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata = std::fs::metadata(path).map_err(|e| {
+        VBoxError::ConfigError(format!("Cannot read passphrase file {}: {}", path.display(), e))
+    })?;
+
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode != 0o600 {
+        return Err(VBoxError::ConfigError(format!(
+            "Refusing to use passphrase file {} with mode {:o}; chmod it to 0600",
+            path.display(),
+            mode
+        )));
+    }
+
+    if metadata.uid() != 0 {
+        return Err(VBoxError::ConfigError(format!(
+            "Refusing to use passphrase file {} owned by uid {}; it must be root-owned",
+            path.display(),
+            metadata.uid()
+        )));
+    }
+
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        VBoxError::ConfigError(format!("Failed to read passphrase file {}: {}", path.display(), e))
+    })?;
+
+    Ok(contents.trim().to_string())
+}
+
+#[cfg(not(unix))]
+fn read_passphrase_file(path: &std::path::Path) -> Result<String> {
+    std::fs::read_to_string(path)
+        .map(|s| s.trim().to_string())
+        .map_err(|e| {
+            VBoxError::ConfigError(format!("Failed to read passphrase file {}: {}", path.display(), e))
+        })
+}
+
+/// Confirms every module `target` is supposed to manage actually resolves
+/// to an on-disk path via `modinfo`, so sign/verify/load fail up front
+/// naming the specific missing module - e.g. a host install missing
+/// `vboxnetflt`/`vboxnetadp`/`vboxpci` - rather than silently operating on
+/// whatever subset `find_modules` happens to discover by filename prefix.
+/// Only meaningful for the built-in VirtualBox target, since
+/// [`SystemPaths::resolve_module_paths`] checks `config.modules`
+/// specifically; auto-detected targets have no declared module list to
+/// check against.
+fn ensure_modules_resolvable(config: &Config, target: &ModuleTarget) -> Result<()> {
+    if target.dkms_package.eq_ignore_ascii_case("virtualbox") {
+        SystemPaths::resolve_module_paths(config)?;
+    }
+    Ok(())
+}
+
+/// Sign command: Sign the modules in `target`, across every kernel in `kernels`
+pub fn sign_command(
+    config: &Config,
+    target: &ModuleTarget,
+    kernels: &KernelSelector,
+    format: OutputFormat,
+) -> Result<()> {
     system::check_root()?;
-    output::print_header("Sign VirtualBox Modules");
-    
+    if !format.is_json() {
+        output::print_header(&format!("Sign {} Modules", target.display_name));
+    }
+
+    ensure_modules_resolvable(config, target)?;
+
     // Check if keys exist
     if !config.keys_exist() {
         return Err(crate::error::VBoxError::KeyNotFound(
             "Signing keys not found. Run 'setup' command first.".to_string(),
         ));
     }
-    
-    // Get passphrase
-    let passphrase = Password::new()
-        .with_prompt("Enter passphrase for signing key")
-        .interact()?;
-    
+
+    let kernel_versions = kernels.resolve()?;
+
+    // Get passphrase - from config.passphrase_file when set (the hook
+    // installed by `install-hook` runs with no terminal to prompt on), or
+    // interactively otherwise.
+    let passphrase = resolve_passphrase(config)?;
+
+    let oplog = logfile::open_if_root();
+    if let Some(ref log_file) = oplog {
+        log_file.banner(&format!("Sign {} Modules", target.display_name));
+    }
+
     // Sign modules
-    signing::sign_all_modules(config, &passphrase)?;
-    
-    output::print_success("All modules signed successfully!");
-    
-    Ok(())
+    let outcomes = signing::sign_all_modules_for_kernels_detailed(
+        target,
+        config,
+        &passphrase,
+        &kernel_versions,
+        oplog.as_ref(),
+    )?;
+
+    let result = signing::sign_outcomes_to_result(target, &outcomes);
+
+    if format.is_json() {
+        output::emit(&outcomes);
+    } else if result.is_ok() {
+        output::print_success(&format!("All {} modules signed successfully!", target.display_name));
+    }
+
+    if config.auto_reload_after_sign && kernels.is_running_kernel_only()? {
+        if !format.is_json() {
+            output::print_info("auto_reload_after_sign is enabled; reloading modules...");
+        }
+        verification::load_modules(target)?;
+    }
+
+    result
 }
 
-/// Verify command: Verify module signatures
-pub fn verify_command() -> Result<()> {
-    output::print_header("Verify Module Signatures");
-    
-    verification::verify_all_modules()?;
-    
-    output::print_success("All modules are properly signed!");
-    
-    Ok(())
+/// Verify command: Verify the signatures of the modules in `target`, across
+/// every kernel in `kernels`
+pub fn verify_command(
+    config: &Config,
+    target: &ModuleTarget,
+    kernels: &KernelSelector,
+    format: OutputFormat,
+) -> Result<()> {
+    if !format.is_json() {
+        output::print_header(&format!("Verify {} Signatures", target.display_name));
+    }
+
+    ensure_modules_resolvable(config, target)?;
+
+    let kernel_versions = kernels.resolve()?;
+    let outcomes = verification::verify_all_modules_for_kernels_detailed(target, config, &kernel_versions)?;
+
+    let result = verification::verify_outcomes_to_result(target, &outcomes);
+
+    if format.is_json() {
+        output::emit(&outcomes);
+    } else if result.is_ok() {
+        output::print_success(&format!("All {} modules are properly signed!", target.display_name));
+    }
+
+    result
 }
 
-/// Load command: Load VirtualBox modules
-pub fn load_command() -> Result<()> {
+/// Load command: Load the modules in `target`
+pub fn load_command(config: &Config, target: &ModuleTarget, format: OutputFormat) -> Result<()> {
     system::check_root()?;
-    output::print_header("Load VirtualBox Modules");
-    
-    verification::load_vbox_modules()?;
-    
-    output::print_success("All VirtualBox modules loaded successfully!");
-    
+    if !format.is_json() {
+        output::print_header(&format!("Load {} Modules", target.display_name));
+    }
+
+    ensure_modules_resolvable(config, target)?;
+
+    verification::load_modules(target)?;
+
+    if !format.is_json() {
+        output::print_success(&format!("All {} modules loaded successfully!", target.display_name));
+    }
+
     Ok(())
 }
 
-/// Rebuild command: Rebuild VirtualBox modules via DKMS
-pub fn rebuild_command() -> Result<()> {
+/// Rebuild command: Rebuild the modules in `target` via DKMS
+pub fn rebuild_command(config: &Config, target: &ModuleTarget, format: OutputFormat) -> Result<()> {
     system::check_root()?;
-    output::print_header("Rebuild VirtualBox Modules");
-    
-    signing::rebuild_vbox_modules()?;
-    
-    output::print_success("VirtualBox modules rebuilt successfully!");
-    
+    if !format.is_json() {
+        output::print_header(&format!("Rebuild {} Modules", target.display_name));
+    }
+
+    let oplog = logfile::open_if_root();
+    if let Some(ref log_file) = oplog {
+        log_file.banner(&format!("Rebuild {} Modules", target.display_name));
+    }
+
+    signing::rebuild_modules(target, config, oplog.as_ref())?;
+
+    if !format.is_json() {
+        output::print_success(&format!("{} modules rebuilt successfully!", target.display_name));
+    }
+
     Ok(())
 }
 
-/// KVM disable command
-pub fn kvm_disable_command(permanent: bool) -> Result<()> {
+/// KVM disable command. `permanent` is the explicit `--permanent` flag;
+/// `config.permanent_kvm_blacklist` makes permanent the default when it
+/// isn't passed.
+pub fn kvm_disable_command(permanent: bool, config: &Config) -> Result<()> {
     system::check_root()?;
     output::print_header("Disable KVM");
-    
+
+    let permanent = permanent || config.permanent_kvm_blacklist;
+
     if permanent {
         output::print_info("Disabling KVM permanently...");
         kvm::disable_kvm_permanent()?;
@@ -167,94 +369,293 @@ pub fn kvm_enable_command() -> Result<()> {
     Ok(())
 }
 
-/// Status command: Show system status
-pub fn status_command(config: &Config) -> Result<()> {
-    output::print_header("System Status");
-    
-    // Kernel version
-    let kernel_version = crate::config::SystemPaths::kernel_version()?;
-    output::print_info(&format!("Kernel version: {}", kernel_version));
-    
-    // Secure Boot status
-    match system::is_secure_boot_enabled() {
-        Ok(true) => output::print_success("Secure Boot: Enabled"),
-        Ok(false) => output::print_warning("Secure Boot: Disabled"),
-        Err(_) => output::print_warning("Secure Boot: Cannot determine"),
-    }
-    
-    // VirtualBox version
-    if system::command_exists("VBoxManage") {
-        match system::execute_command_output("VBoxManage", &["--version"]) {
-            Ok(version) => output::print_info(&format!("VirtualBox version: {}", version)),
-            Err(_) => output::print_warning("VirtualBox: Cannot determine version"),
+/// Status command: Show system status. `scope` narrows the report to the
+/// kernel-side or user-side half (mirroring `status kernel`/`status user`);
+/// `json` (the `status --json` flag) or `format` (the global `--format
+/// json` flag) either one emits the full [`status::StatusReport`] instead
+/// of colored text. Returns an error (and therefore a non-zero exit code)
+/// when modules are unsigned, the MOK isn't enrolled, or KVM is loaded.
+pub fn status_command(
+    config: &Config,
+    json: bool,
+    format: OutputFormat,
+    scope: Option<status::StatusScope>,
+) -> Result<()> {
+    let report = status::build_status_report(config)?;
+
+    if json || format.is_json() {
+        match scope {
+            Some(status::StatusScope::Kernel) => output::emit(&report.kernel),
+            Some(status::StatusScope::User) => output::emit(&report.user),
+            None => output::emit(&report),
         }
     } else {
-        output::print_error("VirtualBox: Not installed");
+        print_status_human(&report, scope);
     }
-    
-    // Signing keys
-    if config.keys_exist() {
-        output::print_success("Signing keys: Present");
+
+    if report.is_healthy() {
+        Ok(())
     } else {
-        output::print_warning("Signing keys: Not found");
+        Err(crate::error::VBoxError::Other(
+            "System is not ready: modules unsigned, MOK not enrolled, or KVM loaded".to_string(),
+        ))
     }
-    
-    // MOK enrollment
-    match mok::is_mok_enrolled(config) {
-        Ok(true) => output::print_success("MOK: Enrolled"),
-        Ok(false) => output::print_warning("MOK: Not enrolled"),
-        Err(_) => output::print_warning("MOK: Cannot determine"),
+}
+
+/// KVM status command: shows whether KVM is loaded (it conflicts with
+/// VirtualBox) and whether it's blacklisted from loading again
+pub fn kvm_status_command(format: OutputFormat) -> Result<()> {
+    let status = kvm::check_kvm_status()?;
+
+    if format.is_json() {
+        output::emit(&serde_json::json!({
+            "kvm_loaded": status.kvm_loaded,
+            "kvm_intel_loaded": status.kvm_intel_loaded,
+            "kvm_amd_loaded": status.kvm_amd_loaded,
+            "blacklisted": status.blacklisted,
+        }));
+        return Ok(());
     }
-    
-    // KVM status
-    match kvm::check_kvm_status() {
-        Ok(status) => {
-            if status.kvm_loaded {
-                output::print_warning("KVM: Loaded (VirtualBox will NOT work!)");
-            } else {
-                output::print_success("KVM: Not loaded (VirtualBox can operate)");
+
+    output::print_header("KVM Status");
+    if status.kvm_loaded {
+        output::print_warning("KVM: Loaded");
+    } else {
+        output::print_success("KVM: Not loaded");
+    }
+    if status.kvm_intel_loaded {
+        println!("  kvm_intel: loaded");
+    }
+    if status.kvm_amd_loaded {
+        println!("  kvm_amd: loaded");
+    }
+    if status.blacklisted {
+        output::print_info("Blacklist: Enabled (permanent)");
+    } else {
+        println!("  Blacklist: disabled");
+    }
+
+    Ok(())
+}
+
+fn print_status_human(report: &status::StatusReport, scope: Option<status::StatusScope>) {
+    output::print_header("System Status");
+
+    if !matches!(scope, Some(status::StatusScope::User)) {
+        let kernel = &report.kernel;
+        output::print_info(&format!("Kernel version: {}", kernel.kernel_version));
+        output::print_info(&format!("Build toolchain: {}", kernel.build_toolchain));
+
+        match kernel.secure_boot_enabled {
+            Some(true) => output::print_success("Secure Boot: Enabled"),
+            Some(false) => output::print_warning("Secure Boot: Disabled"),
+            None => output::print_warning("Secure Boot: Cannot determine"),
+        }
+
+        if kernel.signing_keys_present {
+            output::print_success("Signing keys: Present");
+        } else {
+            output::print_warning("Signing keys: Not found");
+        }
+
+        match kernel.mok_enrolled {
+            Some(true) => output::print_success("MOK: Enrolled"),
+            Some(false) => output::print_warning("MOK: Not enrolled"),
+            None => output::print_warning("MOK: Cannot determine"),
+        }
+
+        if kernel.modules.is_empty() {
+            output::print_warning("Modules: None found");
+        } else {
+            for module in &kernel.modules {
+                if module.signed_by_our_mok {
+                    output::print_success(&format!("Module {}: signed by our MOK", module.name));
+                } else {
+                    output::print_warning(&format!("Module {}: NOT trusted", module.name));
+                }
             }
         }
-        Err(_) => output::print_warning("KVM: Cannot determine status"),
     }
-    
-    // Loaded modules
-    match verification::check_modules_loaded() {
-        Ok(modules) if !modules.is_empty() => {
+
+    if !matches!(scope, Some(status::StatusScope::Kernel)) {
+        let user = &report.user;
+        match &user.virtualbox_version {
+            Some(version) => output::print_info(&format!("VirtualBox version: {}", version)),
+            None => output::print_error("VirtualBox: Not installed"),
+        }
+
+        match user.kvm_loaded {
+            Some(true) => output::print_warning("KVM: Loaded (VirtualBox will NOT work!)"),
+            Some(false) => output::print_success("KVM: Not loaded (VirtualBox can operate)"),
+            None => output::print_warning("KVM: Cannot determine status"),
+        }
+
+        if user.modules_loaded.is_empty() {
+            output::print_info("VirtualBox modules: Not loaded");
+        } else {
             output::print_success(&format!(
                 "VirtualBox modules loaded: {}",
-                modules.join(", ")
+                user.modules_loaded.join(", ")
             ));
         }
-        Ok(_) => output::print_info("VirtualBox modules: Not loaded"),
-        Err(_) => output::print_warning("VirtualBox modules: Cannot determine"),
     }
-    
-    Ok(())
 }
 
-/// Full command: Rebuild, sign, verify, and load
-pub fn full_command(config: &Config) -> Result<()> {
+/// Full command: Rebuild, sign, verify, and load the modules in `target`.
+/// Rebuild and load always target the running kernel (DKMS rebuilds what's
+/// currently installed, and only the running kernel can `modprobe` a
+/// module); `kernels` only widens the sign/verify steps.
+pub fn full_command(
+    config: &Config,
+    target: &ModuleTarget,
+    kernels: &KernelSelector,
+    format: OutputFormat,
+) -> Result<()> {
     system::check_root()?;
-    output::print_header("Full Process: Rebuild, Sign, Verify, and Load");
-    
+    if !format.is_json() {
+        output::print_header(&format!(
+            "Full Process for {}: Rebuild, Sign, Verify, and Load",
+            target.display_name
+        ));
+    }
+
+    if let Some(log_file) = logfile::open_if_root() {
+        log_file.banner(&format!(
+            "Full Process for {}: Rebuild, Sign, Verify, and Load",
+            target.display_name
+        ));
+    }
+
     // Rebuild
-    output::print_section("Step 1/4: Rebuilding modules");
-    rebuild_command()?;
-    
+    if !format.is_json() {
+        output::print_section("Step 1/4: Rebuilding modules");
+    }
+    rebuild_command(config, target, format)?;
+
     // Sign
-    output::print_section("Step 2/4: Signing modules");
-    sign_command(config)?;
-    
+    if !format.is_json() {
+        output::print_section("Step 2/4: Signing modules");
+    }
+    sign_command(config, target, kernels, format)?;
+
     // Verify
-    output::print_section("Step 3/4: Verifying signatures");
-    verify_command()?;
-    
+    if !format.is_json() {
+        output::print_section("Step 3/4: Verifying signatures");
+    }
+    verify_command(config, target, kernels, format)?;
+
     // Load
-    output::print_section("Step 4/4: Loading modules");
-    load_command()?;
-    
-    output::print_success("Full process completed successfully!");
-    
+    if !format.is_json() {
+        output::print_section("Step 4/4: Loading modules");
+    }
+    load_command(config, target, format)?;
+
+    if !format.is_json() {
+        output::print_success("Full process completed successfully!");
+    }
+
+    Ok(())
+}
+
+/// Install-hook command: installs the kernel-upgrade hook (DKMS
+/// `post_build.d` and, where present, APT/pacman) that re-signs the modules
+/// of every installed kernel non-interactively right after a new kernel's
+/// modules are built.
+pub fn install_hook_command(config: &Config) -> Result<()> {
+    output::print_header("Install Kernel-Upgrade Hook");
+
+    hook::install_hook(config)?;
+
+    if config.passphrase_file.is_none() {
+        output::print_warning(
+            "No passphrase_file configured - the hook will fail to sign until you set one \
+             (a root-only, mode-0600 file path) in config.toml",
+        );
+    }
+
+    output::print_success("Kernel-upgrade hook installed");
+
+    Ok(())
+}
+
+/// Uninstall-hook command: removes any hooks `install-hook` previously
+/// installed. Safe to run even if none were.
+pub fn uninstall_hook_command() -> Result<()> {
+    output::print_header("Uninstall Kernel-Upgrade Hook");
+
+    hook::uninstall_hook()?;
+
+    output::print_success("Kernel-upgrade hook removed");
+
+    Ok(())
+}
+
+/// Install-service command: installs and enables the `vbox-sb-manager`
+/// systemd oneshot unit (runs `full` ahead of `display-manager.service`),
+/// plus the companion kernel-upgrade hook.
+pub fn install_service_command(config: &Config) -> Result<()> {
+    output::print_header("Install Systemd Service");
+
+    service::install_service(config)?;
+
+    output::print_success("vbox-sb-manager.service installed and enabled");
+
+    Ok(())
+}
+
+/// Uninstall-service command: disables and removes the systemd unit and its
+/// companion hook. Safe to run even if `install-service` never ran.
+pub fn uninstall_service_command() -> Result<()> {
+    output::print_header("Uninstall Systemd Service");
+
+    service::uninstall_service()?;
+
+    output::print_success("vbox-sb-manager.service removed");
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernel_selector_from_args_defaults_to_running() {
+        assert!(matches!(
+            KernelSelector::from_args(None, false),
+            KernelSelector::Running
+        ));
+    }
+
+    #[test]
+    fn test_kernel_selector_from_args_prefers_all_kernels() {
+        // clap's `conflicts_with` already prevents both being set at once,
+        // but if it ever didn't, --all-kernels should win.
+        assert!(matches!(
+            KernelSelector::from_args(Some("5.15.0-generic"), true),
+            KernelSelector::All
+        ));
+    }
+
+    #[test]
+    fn test_kernel_selector_from_args_specific_kernel() {
+        match KernelSelector::from_args(Some("5.15.0-generic"), false) {
+            KernelSelector::Specific(version) => assert_eq!(version, "5.15.0-generic"),
+            other => panic!("expected Specific, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_kernel_selector_running_resolves_to_one_version() {
+        let versions = KernelSelector::Running.resolve().unwrap();
+        assert_eq!(versions.len(), 1);
+    }
+
+    #[test]
+    fn test_kernel_selector_specific_resolves_verbatim() {
+        let versions = KernelSelector::Specific("5.15.0-generic".to_string())
+            .resolve()
+            .unwrap();
+        assert_eq!(versions, vec!["5.15.0-generic".to_string()]);
+    }
+}
@@ -1,9 +1,11 @@
 //! Interactive menu mode
 
-use crate::cli::commands;
+use crate::cli::commands::{self, KernelSelector};
 use crate::config::Config;
 use crate::error::Result;
+use crate::modules::target::ModuleTarget;
 use crate::utils::output;
+use crate::utils::output::OutputFormat;
 use dialoguer::{theme::ColorfulTheme, Select};
 
 /// Menu options
@@ -18,6 +20,10 @@ enum MenuOption {
     KvmDisable,
     KvmEnable,
     Status,
+    InstallHook,
+    UninstallHook,
+    InstallService,
+    UninstallService,
     Exit,
 }
 
@@ -33,10 +39,14 @@ impl MenuOption {
             MenuOption::KvmDisable => "Disable KVM",
             MenuOption::KvmEnable => "Enable KVM",
             MenuOption::Status => "System Status",
+            MenuOption::InstallHook => "Install Kernel-Upgrade Hook",
+            MenuOption::UninstallHook => "Uninstall Kernel-Upgrade Hook",
+            MenuOption::InstallService => "Install Systemd Service (re-sign + load before display-manager)",
+            MenuOption::UninstallService => "Uninstall Systemd Service",
             MenuOption::Exit => "Exit",
         }
     }
-    
+
     fn all_options() -> Vec<Self> {
         vec![
             MenuOption::Setup,
@@ -48,6 +58,10 @@ impl MenuOption {
             MenuOption::KvmDisable,
             MenuOption::KvmEnable,
             MenuOption::Status,
+            MenuOption::InstallHook,
+            MenuOption::UninstallHook,
+            MenuOption::InstallService,
+            MenuOption::UninstallService,
             MenuOption::Exit,
         ]
     }
@@ -68,25 +82,37 @@ pub fn run_interactive(config: &Config) -> Result<()> {
             .interact()?;
         
         let selected_option = options[selection];
-        
+
         println!(); // Add spacing
-        
+
+        // The interactive menu always targets VirtualBox; `--target` is a
+        // non-interactive CLI-only option for the other DKMS packages.
+        // Likewise it always operates on the running kernel; `--kernel`/
+        // `--all-kernels` are CLI-only.
+        let target = ModuleTarget::vbox(config);
+        let kernels = KernelSelector::Running;
+        let format = OutputFormat::Human;
+
         let result = match selected_option {
             MenuOption::Setup => commands::setup_command(config),
-            MenuOption::Rebuild => commands::rebuild_command(),
-            MenuOption::Sign => commands::sign_command(config),
-            MenuOption::Verify => commands::verify_command(),
-            MenuOption::Load => commands::load_command(),
-            MenuOption::Full => commands::full_command(config),
+            MenuOption::Rebuild => commands::rebuild_command(config, &target, format),
+            MenuOption::Sign => commands::sign_command(config, &target, &kernels, format),
+            MenuOption::Verify => commands::verify_command(config, &target, &kernels, format),
+            MenuOption::Load => commands::load_command(config, &target, format),
+            MenuOption::Full => commands::full_command(config, &target, &kernels, format),
             MenuOption::KvmDisable => {
                 let permanent = dialoguer::Confirm::new()
                     .with_prompt("Disable KVM permanently (survives reboot)?")
                     .default(false)
                     .interact()?;
-                commands::kvm_disable_command(permanent)
+                commands::kvm_disable_command(permanent, config)
             }
             MenuOption::KvmEnable => commands::kvm_enable_command(),
-            MenuOption::Status => commands::status_command(config),
+            MenuOption::Status => commands::status_command(config, false, format, None),
+            MenuOption::InstallHook => commands::install_hook_command(config),
+            MenuOption::UninstallHook => commands::uninstall_hook_command(),
+            MenuOption::InstallService => commands::install_service_command(config),
+            MenuOption::UninstallService => commands::uninstall_service_command(),
             MenuOption::Exit => {
                 output::print_info("Exiting...");
                 break;
@@ -0,0 +1,229 @@
+//! Structured system status, shared by the human-readable `status` command
+//! and its `--json` / `status kernel` / `status user` variants.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::modules::{mok, toolchain, verification};
+use crate::utils::system;
+use serde::Serialize;
+
+/// Which half of the status report to show, mirroring the
+/// `status-kernel`/`status-user` split in upstream's `vboxadd.sh`.
+#[derive(Debug, Clone, Copy, clap::Subcommand)]
+pub enum StatusScope {
+    /// Kernel-side state: modules, signing, Secure Boot, MOK
+    Kernel,
+    /// Userspace state: VBoxManage, KVM conflict, loaded modules
+    User,
+}
+
+/// Signed/loaded state of a single managed module
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleStatus {
+    pub name: String,
+    pub signed_by_our_mok: bool,
+    pub loaded: bool,
+}
+
+/// Kernel-side status: what it takes for the modules to load under Secure Boot
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelStatus {
+    pub kernel_version: String,
+    pub build_toolchain: String,
+    pub secure_boot_enabled: Option<bool>,
+    pub signing_keys_present: bool,
+    pub mok_enrolled: Option<bool>,
+    pub mok_subject: Option<String>,
+    pub mok_expiry: Option<String>,
+    pub modules: Vec<ModuleStatus>,
+}
+
+/// Userspace status: what it takes for VirtualBox to actually run a VM
+#[derive(Debug, Clone, Serialize)]
+pub struct UserStatus {
+    pub virtualbox_version: Option<String>,
+    pub kvm_loaded: Option<bool>,
+    pub kvm_blacklisted: Option<bool>,
+    pub modules_loaded: Vec<String>,
+}
+
+/// Full status report, serialized as-is for `--json`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub kernel: KernelStatus,
+    pub user: UserStatus,
+}
+
+impl StatusReport {
+    /// True when there is nothing actionable left: all modules are signed
+    /// by our MOK, the MOK is enrolled, and KVM isn't holding the hardware.
+    /// `status_command` uses this to pick a non-zero exit code.
+    pub fn is_healthy(&self) -> bool {
+        let modules_ok = !self.kernel.modules.is_empty()
+            && self.kernel.modules.iter().all(|m| m.signed_by_our_mok);
+        let mok_ok = self.kernel.mok_enrolled.unwrap_or(false);
+        let kvm_ok = !self.user.kvm_loaded.unwrap_or(false);
+
+        modules_ok && mok_ok && kvm_ok
+    }
+}
+
+/// Builds the full status report
+pub fn build_status_report(config: &Config) -> Result<StatusReport> {
+    Ok(StatusReport {
+        kernel: build_kernel_status(config),
+        user: build_user_status(config),
+    })
+}
+
+fn build_kernel_status(config: &Config) -> KernelStatus {
+    let kernel_version = crate::config::SystemPaths::kernel_version().unwrap_or_default();
+    let build_toolchain = toolchain::describe_toolchain();
+    let secure_boot_enabled = system::is_secure_boot_enabled().ok();
+    let signing_keys_present = config.keys_exist();
+    let mok_enrolled = mok::is_mok_enrolled(config).ok();
+    let (mok_subject, mok_expiry) = describe_mok_cert(config);
+
+    let target = crate::modules::target::ModuleTarget::vbox(config);
+    let modules = crate::modules::signing::find_modules(&target, config)
+        .map(|modules| {
+            modules
+                .iter()
+                .map(|module| ModuleStatus {
+                    name: module.name.clone(),
+                    signed_by_our_mok: verification::verify_module_signature(module, config)
+                        .map(|status| status.is_trusted())
+                        .unwrap_or(false),
+                    loaded: system::is_module_loaded(&module.name).unwrap_or(false),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    KernelStatus {
+        kernel_version,
+        build_toolchain,
+        secure_boot_enabled,
+        signing_keys_present,
+        mok_enrolled,
+        mok_subject,
+        mok_expiry,
+        modules,
+    }
+}
+
+fn build_user_status(config: &Config) -> UserStatus {
+    let virtualbox_version = if system::command_exists("VBoxManage") {
+        system::execute_command_output("VBoxManage", &["--version"]).ok()
+    } else {
+        None
+    };
+
+    let kvm_status = crate::modules::kvm::check_kvm_status().ok();
+    let target = crate::modules::target::ModuleTarget::vbox(config);
+    let modules_loaded = verification::check_modules_loaded(&target).unwrap_or_default();
+
+    UserStatus {
+        virtualbox_version,
+        kvm_loaded: kvm_status.as_ref().map(|s| s.kvm_loaded),
+        kvm_blacklisted: kvm_status.as_ref().map(|s| s.blacklisted),
+        modules_loaded,
+    }
+}
+
+/// Parses the subject and expiry date out of our enrolled certificate
+fn describe_mok_cert(config: &Config) -> (Option<String>, Option<String>) {
+    if !config.public_key.exists() {
+        return (None, None);
+    }
+
+    let subject = system::execute_command_output(
+        "openssl",
+        &[
+            "x509",
+            "-inform",
+            "DER",
+            "-in",
+            config.public_key.to_str().unwrap_or_default(),
+            "-noout",
+            "-subject",
+        ],
+    )
+    .ok();
+
+    let expiry = system::execute_command_output(
+        "openssl",
+        &[
+            "x509",
+            "-inform",
+            "DER",
+            "-in",
+            config.public_key.to_str().unwrap_or_default(),
+            "-noout",
+            "-enddate",
+        ],
+    )
+    .ok();
+
+    (subject, expiry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_healthy_requires_nonempty_modules() {
+        let report = StatusReport {
+            kernel: KernelStatus {
+                kernel_version: "test".to_string(),
+                build_toolchain: "gcc".to_string(),
+                secure_boot_enabled: Some(true),
+                signing_keys_present: true,
+                mok_enrolled: Some(true),
+                mok_subject: None,
+                mok_expiry: None,
+                modules: vec![],
+            },
+            user: UserStatus {
+                virtualbox_version: None,
+                kvm_loaded: Some(false),
+                kvm_blacklisted: Some(false),
+                modules_loaded: vec![],
+            },
+        };
+
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn test_status_report_serializes_to_json() {
+        let report = StatusReport {
+            kernel: KernelStatus {
+                kernel_version: "6.8.0-generic".to_string(),
+                build_toolchain: "gcc".to_string(),
+                secure_boot_enabled: Some(true),
+                signing_keys_present: true,
+                mok_enrolled: Some(true),
+                mok_subject: Some("CN=test".to_string()),
+                mok_expiry: Some("Jan 1 00:00:00 2030 GMT".to_string()),
+                modules: vec![ModuleStatus {
+                    name: "vboxdrv".to_string(),
+                    signed_by_our_mok: true,
+                    loaded: true,
+                }],
+            },
+            user: UserStatus {
+                virtualbox_version: Some("7.0.0".to_string()),
+                kvm_loaded: Some(false),
+                kvm_blacklisted: Some(true),
+                modules_loaded: vec!["vboxdrv".to_string()],
+            },
+        };
+
+        let json = serde_json::to_string(&report).expect("StatusReport must serialize");
+        assert!(json.contains("\"kernel_version\":\"6.8.0-generic\""));
+        assert!(json.contains("\"virtualbox_version\":\"7.0.0\""));
+        assert!(json.contains("\"signed_by_our_mok\":true"));
+    }
+}
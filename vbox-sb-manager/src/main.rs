@@ -8,7 +8,9 @@
 
 use clap::{Parser, Subcommand};
 use log::LevelFilter;
-use virtualbox_secure_boot_manager::{cli, config::Config, utils};
+use virtualbox_secure_boot_manager::{
+    cli, config::Config, modules::target::ModuleTarget, utils, utils::output::OutputFormat, Result,
+};
 
 #[derive(Parser)]
 #[command(
@@ -25,7 +27,22 @@ struct Cli {
     /// Enable debug logging
     #[arg(short, long, global = true)]
     debug: bool,
-    
+
+    /// DKMS package to operate on (sign/verify/load/rebuild/full). Defaults
+    /// to VirtualBox; pass "all" to operate on every DKMS-managed package.
+    #[arg(long, global = true)]
+    target: Option<String>,
+
+    /// Path to an additional config.toml, layered on top of
+    /// /etc/virtualbox-sb-manager/config.toml and ~/.config/.../config.toml
+    #[arg(long, global = true)]
+    config: Option<std::path::PathBuf>,
+
+    /// Output format for status, signing/verification summaries, and KVM
+    /// status - "human" (colored text) or "json" (for CI/Ansible/monitoring)
+    #[arg(long, global = true, value_enum, default_value = "human")]
+    format: OutputFormat,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -36,19 +53,28 @@ enum Commands {
     Setup,
     
     /// Sign VirtualBox kernel modules
-    Sign,
-    
+    Sign {
+        #[command(flatten)]
+        kernels: KernelArgs,
+    },
+
     /// Verify module signatures
-    Verify,
-    
+    Verify {
+        #[command(flatten)]
+        kernels: KernelArgs,
+    },
+
     /// Load VirtualBox kernel modules
     Load,
-    
+
     /// Rebuild VirtualBox modules via DKMS
     Rebuild,
-    
+
     /// Full process: rebuild, sign, verify, and load
-    Full,
+    Full {
+        #[command(flatten)]
+        kernels: KernelArgs,
+    },
     
     /// KVM management commands
     Kvm {
@@ -57,12 +83,59 @@ enum Commands {
     },
     
     /// Show system status
-    Status,
-    
+    Status {
+        /// Emit a machine-readable JSON report instead of colored text
+        #[arg(long)]
+        json: bool,
+
+        #[command(subcommand)]
+        scope: Option<virtualbox_secure_boot_manager::cli::status::StatusScope>,
+    },
+
+    /// Install the kernel-upgrade hook (DKMS post_build.d and, where
+    /// present, APT/pacman) so modules are re-signed automatically after
+    /// `apt upgrade`/`dkms build` instead of silently failing to load
+    InstallHook,
+
+    /// Remove the kernel-upgrade hook installed by `install-hook`
+    UninstallHook,
+
+    /// Install and enable the vbox-sb-manager systemd oneshot unit (runs
+    /// `full` ahead of display-manager.service) plus its companion
+    /// kernel-upgrade hook
+    InstallService,
+
+    /// Disable and remove the systemd unit installed by `install-service`
+    UninstallService,
+
     /// Launch interactive menu mode
     Interactive,
 }
 
+/// `--kernel <ver>` / `--all-kernels` selector, shared by `sign`, `verify`,
+/// and `full`. Pre-sign a newly installed kernel's modules with `--kernel
+/// <ver>` before rebooting into it, or catch up every installed kernel at
+/// once with `--all-kernels`. Defaults to the running kernel.
+#[derive(clap::Args)]
+struct KernelArgs {
+    /// Operate on one specific installed kernel version instead of the
+    /// running one (e.g. the version of a just-installed kernel you haven't
+    /// rebooted into yet)
+    #[arg(long)]
+    kernel: Option<String>,
+
+    /// Operate on every kernel version under /lib/modules, not just the
+    /// running one
+    #[arg(long, conflicts_with = "kernel")]
+    all_kernels: bool,
+}
+
+impl KernelArgs {
+    fn selector(&self) -> cli::commands::KernelSelector {
+        cli::commands::KernelSelector::from_args(self.kernel.as_deref(), self.all_kernels)
+    }
+}
+
 #[derive(Subcommand)]
 enum KvmAction {
     /// Disable KVM temporarily (until reboot)
@@ -79,6 +152,32 @@ enum KvmAction {
     Status,
 }
 
+/// Resolves `--target` and runs `f` once per resulting target, continuing
+/// through failures so `--target all` signs/verifies/loads every package it
+/// can before reporting the first failure.
+fn run_for_targets(
+    target_arg: Option<&str>,
+    config: &Config,
+    mut f: impl FnMut(&ModuleTarget) -> Result<()>,
+) -> Result<()> {
+    let targets = cli::commands::resolve_targets(target_arg, config)?;
+    let mut first_error = None;
+
+    for target in &targets {
+        if let Err(e) = f(target) {
+            log::error!("{}: {}", target.display_name, e);
+            if first_error.is_none() {
+                first_error = Some(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
     
@@ -92,8 +191,14 @@ fn main() {
     };
     
     // Initialize logger
-    let config = Config::default();
-    if let Err(e) = utils::logger::VBoxLogger::init(&config.log_file, log_level) {
+    let mut config = Config::load(cli.config.as_deref());
+    config.autodetect_modules();
+    if let Err(e) = utils::logger::VBoxLogger::init_with_rotation(
+        &config.log_file,
+        log_level,
+        config.log_max_bytes,
+        config.log_max_generations,
+    ) {
         eprintln!("Warning: Failed to initialize logger: {}", e);
         // Continue without file logging
     }
@@ -102,44 +207,48 @@ fn main() {
     log::info!("Version: {}", env!("CARGO_PKG_VERSION"));
     
     // Execute command
+    let target_arg = cli.target.clone();
+    let format = cli.format;
     let result = match cli.command {
         Some(Commands::Setup) => cli::commands::setup_command(&config),
-        Some(Commands::Sign) => cli::commands::sign_command(&config),
-        Some(Commands::Verify) => cli::commands::verify_command(),
-        Some(Commands::Load) => cli::commands::load_command(),
-        Some(Commands::Rebuild) => cli::commands::rebuild_command(),
-        Some(Commands::Full) => cli::commands::full_command(&config),
+        Some(Commands::Sign { kernels }) => {
+            let kernels = kernels.selector();
+            run_for_targets(target_arg.as_deref(), &config, |target| {
+                cli::commands::sign_command(&config, target, &kernels, format)
+            })
+        }
+        Some(Commands::Verify { kernels }) => {
+            let kernels = kernels.selector();
+            run_for_targets(target_arg.as_deref(), &config, |target| {
+                cli::commands::verify_command(&config, target, &kernels, format)
+            })
+        }
+        Some(Commands::Load) => run_for_targets(target_arg.as_deref(), &config, |target| {
+            cli::commands::load_command(&config, target, format)
+        }),
+        Some(Commands::Rebuild) => run_for_targets(target_arg.as_deref(), &config, |target| {
+            cli::commands::rebuild_command(&config, target, format)
+        }),
+        Some(Commands::Full { kernels }) => {
+            let kernels = kernels.selector();
+            run_for_targets(target_arg.as_deref(), &config, |target| {
+                cli::commands::full_command(&config, target, &kernels, format)
+            })
+        }
         Some(Commands::Kvm { action }) => match action {
-            KvmAction::Disable { permanent } => cli::commands::kvm_disable_command(permanent),
-            KvmAction::Enable => cli::commands::kvm_enable_command(),
-            KvmAction::Status => {
-                use virtualbox_secure_boot_manager::modules::kvm;
-                match kvm::check_kvm_status() {
-                    Ok(status) => {
-                        utils::output::print_header("KVM Status");
-                        if status.kvm_loaded {
-                            utils::output::print_warning("KVM: Loaded");
-                        } else {
-                            utils::output::print_success("KVM: Not loaded");
-                        }
-                        if status.kvm_intel_loaded {
-                            println!("  kvm_intel: loaded");
-                        }
-                        if status.kvm_amd_loaded {
-                            println!("  kvm_amd: loaded");
-                        }
-                        if status.blacklisted {
-                            utils::output::print_info("Blacklist: Enabled (permanent)");
-                        } else {
-                            println!("  Blacklist: disabled");
-                        }
-                        Ok(())
-                    }
-                    Err(e) => Err(e),
-                }
+            KvmAction::Disable { permanent } => {
+                cli::commands::kvm_disable_command(permanent, &config)
             }
+            KvmAction::Enable => cli::commands::kvm_enable_command(),
+            KvmAction::Status => cli::commands::kvm_status_command(format),
         },
-        Some(Commands::Status) => cli::commands::status_command(&config),
+        Some(Commands::Status { json, scope }) => {
+            cli::commands::status_command(&config, json, format, scope)
+        }
+        Some(Commands::InstallHook) => cli::commands::install_hook_command(&config),
+        Some(Commands::UninstallHook) => cli::commands::uninstall_hook_command(),
+        Some(Commands::InstallService) => cli::commands::install_service_command(&config),
+        Some(Commands::UninstallService) => cli::commands::uninstall_service_command(),
         Some(Commands::Interactive) => cli::interactive::run_interactive(&config),
         None => {
             // No command specified, run interactive mode
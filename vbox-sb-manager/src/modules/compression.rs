@@ -0,0 +1,174 @@
+//! In-process module (de)compression.
+//!
+//! Signing a compressed `.ko.{xz,gz,zst}` used to shell out to `xz`/`gunzip`/
+//! `gzip`/`zstd`, which fails outright on minimal systems missing those
+//! binaries. This does the same work with native Rust codecs, so sign/verify
+//! only ever depend on `openssl`, `sign-file`, and `modinfo` being present.
+
+use crate::error::{Result, VBoxError};
+use crate::modules::signing::CompressionType;
+use nix::unistd::{Gid, Uid};
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Decompresses `compressed_path` (in `compression_type` format) to a
+/// sibling file with the compression suffix stripped. The write is atomic
+/// (temp file + rename) and the original file's mode is preserved.
+pub fn decompress(compressed_path: &Path, compression_type: &CompressionType) -> Result<PathBuf> {
+    let decompressed_path = compressed_path.with_extension("");
+    let data = decode(compressed_path, compression_type)?;
+    atomic_write(&decompressed_path, &data, compressed_path)?;
+    Ok(decompressed_path)
+}
+
+/// Recompresses `decompressed_path` in place, writing
+/// `decompressed_path.<ext>` and removing the uncompressed file on success.
+pub fn recompress(
+    decompressed_path: &Path,
+    compression_type: &CompressionType,
+) -> Result<PathBuf> {
+    let data = fs::read(decompressed_path)?;
+    let encoded = encode(&data, compression_type)?;
+
+    let compressed_path = with_compression_suffix(decompressed_path, compression_type);
+    atomic_write(&compressed_path, &encoded, decompressed_path)?;
+    fs::remove_file(decompressed_path)?;
+
+    Ok(compressed_path)
+}
+
+fn decode(path: &Path, compression_type: &CompressionType) -> Result<Vec<u8>> {
+    let file = File::open(path)?;
+    let mut out = Vec::new();
+
+    let result = match compression_type {
+        CompressionType::Xz => xz2::read::XzDecoder::new(file).read_to_end(&mut out),
+        CompressionType::Gz => flate2::read::GzDecoder::new(file).read_to_end(&mut out),
+        CompressionType::Zst => zstd::stream::Decoder::new(file)
+            .map_err(std::io::Error::from)?
+            .read_to_end(&mut out),
+    };
+
+    result.map_err(|e| {
+        VBoxError::Other(format!("failed to decompress {}: {}", path.display(), e))
+    })?;
+
+    Ok(out)
+}
+
+fn encode(data: &[u8], compression_type: &CompressionType) -> Result<Vec<u8>> {
+    let result: std::io::Result<Vec<u8>> = match compression_type {
+        CompressionType::Xz => {
+            // Default preset (6), matching the `xz` CLI's default.
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(data).and_then(|_| encoder.finish())
+        }
+        CompressionType::Gz => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).and_then(|_| encoder.finish())
+        }
+        CompressionType::Zst => {
+            // Default level (0 means "let zstd pick its default", same as
+            // the `zstd` CLI with no `-#` flag).
+            zstd::stream::encode_all(data, 0)
+        }
+    };
+
+    result.map_err(|e| VBoxError::Other(format!("failed to compress module: {}", e)))
+}
+
+fn with_compression_suffix(path: &Path, compression_type: &CompressionType) -> PathBuf {
+    let suffix = match compression_type {
+        CompressionType::Xz => "xz",
+        CompressionType::Gz => "gz",
+        CompressionType::Zst => "zst",
+    };
+
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Writes `data` to a temp file next to `target` and renames it into place
+/// so a crash mid-write never leaves a half-written module, copying the mode
+/// and ownership from `mode_source` (the file being replaced) - otherwise
+/// the rebuilt module would come back owned by whatever user ran sign/verify
+/// instead of root.
+fn atomic_write(target: &Path, data: &[u8], mode_source: &Path) -> Result<()> {
+    let tmp_path = target.with_file_name(format!(
+        "{}.tmp",
+        target.file_name().and_then(|n| n.to_str()).unwrap_or("module")
+    ));
+
+    fs::write(&tmp_path, data)?;
+
+    if let Ok(metadata) = fs::metadata(mode_source) {
+        let mut perms = fs::metadata(&tmp_path)?.permissions();
+        perms.set_mode(metadata.permissions().mode());
+        fs::set_permissions(&tmp_path, perms)?;
+
+        nix::unistd::chown(
+            &tmp_path,
+            Some(Uid::from_raw(metadata.uid())),
+            Some(Gid::from_raw(metadata.gid())),
+        )?;
+    }
+
+    fs::rename(&tmp_path, target)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_gz_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let original = b"fake kernel module contents";
+
+        let mut encoder =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(original).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let compressed_path = dir.path().join("mod.ko.gz");
+        fs::write(&compressed_path, &compressed).unwrap();
+
+        let decompressed_path = decompress(&compressed_path, &CompressionType::Gz).unwrap();
+        assert_eq!(fs::read(&decompressed_path).unwrap(), original);
+
+        let recompressed_path = recompress(&decompressed_path, &CompressionType::Gz).unwrap();
+        assert_eq!(recompressed_path, compressed_path);
+        assert!(!decompressed_path.exists());
+
+        let roundtripped = decompress(&recompressed_path, &CompressionType::Gz).unwrap();
+        assert_eq!(fs::read(&roundtripped).unwrap(), original);
+    }
+
+    #[test]
+    fn test_atomic_write_preserves_mode_and_ownership() {
+        let dir = TempDir::new().unwrap();
+
+        let source_path = dir.path().join("mod.ko");
+        fs::write(&source_path, b"original contents").unwrap();
+        let mut perms = fs::metadata(&source_path).unwrap().permissions();
+        perms.set_mode(0o640);
+        fs::set_permissions(&source_path, perms).unwrap();
+        let source_metadata = fs::metadata(&source_path).unwrap();
+
+        let target_path = dir.path().join("mod.ko.new");
+        atomic_write(&target_path, b"new contents", &source_path).unwrap();
+        let target_metadata = fs::metadata(&target_path).unwrap();
+
+        assert_eq!(target_metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!(target_metadata.uid(), source_metadata.uid());
+        assert_eq!(target_metadata.gid(), source_metadata.gid());
+    }
+}
@@ -0,0 +1,226 @@
+//! Kernel-upgrade hook.
+//!
+//! Without this, a user has to remember to run `full` by hand after every
+//! kernel update or their VirtualBox modules silently fail to load under
+//! Secure Boot. `install_hook` drops a DKMS `post_build.d` script (run right
+//! after DKMS rebuilds modules for a newly installed kernel) and, where the
+//! package manager supports it, an APT or pacman hook that does the same
+//! thing on `apt upgrade`/`pacman -Syu`. Both just re-exec this binary's
+//! `sign --target all --all-kernels` non-interactively, so the just-built
+//! kernel's modules are signed before it is booted into. The passphrase
+//! must come from
+//! `config.passphrase_file` (see [`crate::cli::commands::resolve_passphrase`])
+//! rather than a prompt - there's no terminal to prompt on.
+
+use crate::config::Config;
+use crate::error::{Result, VBoxError};
+use crate::utils::system;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// DKMS runs every executable script under here after building modules for
+/// a kernel, with `$kernelver` etc. set in the environment.
+const DKMS_POST_BUILD_DIR: &str = "/etc/dkms/post_build.d";
+
+/// APT runs every command listed in a `DPkg::Post-Invoke` drop-in after each
+/// invocation (install/upgrade/remove), including kernel package upgrades.
+const APT_HOOK_PATH: &str = "/etc/apt/apt.conf.d/90virtualbox-sb-manager";
+
+/// Pacman's equivalent: a hook file fired after a `linux*` package
+/// transaction completes.
+const PACMAN_HOOK_PATH: &str = "/etc/pacman.d/hooks/90-virtualbox-sb-manager.hook";
+
+/// Written into every managed file so `uninstall_hook` only ever removes
+/// files this tool created.
+const HOOK_MARKER: &str = "# managed-by: virtualbox-sb-manager install-hook";
+
+fn dkms_hook_path() -> PathBuf {
+    Path::new(DKMS_POST_BUILD_DIR).join("virtualbox-sb-manager.sh")
+}
+
+/// Path to the binary the hooks should re-exec. Resolved once at install
+/// time so the hook keeps working even if `PATH` looks different in DKMS's
+/// or APT's stripped-down hook environment.
+pub(crate) fn resolve_self_path() -> Result<PathBuf> {
+    std::env::current_exe()
+        .map_err(|e| VBoxError::Other(format!("Failed to resolve own executable path: {}", e)))
+}
+
+fn dkms_hook_script(bin: &Path) -> String {
+    format!(
+        "#!/bin/sh\n\
+         {marker}\n\
+         # Runs after DKMS builds modules for a (possibly new) kernel.\n\
+         # \"|| true\" so a kernel with no matching modules - or a\n\
+         # not-yet-configured passphrase file - doesn't fail the kernel\n\
+         # install/upgrade that triggered this hook.\n\
+         {bin} sign --target all --all-kernels >>/var/log/vbox-secure-boot-manager.log 2>&1 || true\n",
+        marker = HOOK_MARKER,
+        bin = bin.display(),
+    )
+}
+
+fn apt_hook_script(bin: &Path) -> String {
+    format!(
+        "{marker}\n\
+         DPkg::Post-Invoke {{ \"{bin} sign --target all --all-kernels >>/var/log/vbox-secure-boot-manager.log 2>&1 || true\"; }};\n",
+        marker = HOOK_MARKER,
+        bin = bin.display(),
+    )
+}
+
+fn pacman_hook_script(bin: &Path) -> String {
+    format!(
+        "{marker}\n\
+         [Trigger]\n\
+         Operation = Install\n\
+         Operation = Upgrade\n\
+         Type = Package\n\
+         Target = linux*\n\
+         \n\
+         [Action]\n\
+         Description = Re-sign VirtualBox kernel modules for Secure Boot\n\
+         When = PostTransaction\n\
+         Exec = {bin} sign --target all --all-kernels\n",
+        marker = HOOK_MARKER,
+        bin = bin.display(),
+    )
+}
+
+/// Writes `contents` to `path` with mode 0755 (the hook runners all expect
+/// their scripts to be executable), creating the parent directory if
+/// needed. Writing is naturally idempotent: running `install_hook` twice
+/// just overwrites the same content with itself.
+fn write_hook_script(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            VBoxError::Other(format!("Failed to create {}: {}", parent.display(), e))
+        })?;
+    }
+
+    fs::write(path, contents)
+        .map_err(|e| VBoxError::Other(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(path, perms)?;
+    }
+
+    log::info!("Installed hook: {}", path.display());
+    Ok(())
+}
+
+/// Removes a hook file this tool installed, leaving anything else alone. Not
+/// finding the file (or the marker) is not an error - `uninstall_hook` must
+/// be safe to run whether or not `install_hook` ever ran.
+fn remove_hook_script(path: &Path) -> Result<()> {
+    match fs::read_to_string(path) {
+        Ok(contents) if contents.contains(HOOK_MARKER) => {
+            fs::remove_file(path).map_err(|e| {
+                VBoxError::Other(format!("Failed to remove {}: {}", path.display(), e))
+            })?;
+            log::info!("Removed hook: {}", path.display());
+        }
+        Ok(_) => {
+            log::warn!(
+                "{} exists but wasn't installed by us; leaving it alone",
+                path.display()
+            );
+        }
+        Err(_) => {
+            log::debug!("{} not present; nothing to remove", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Installs the DKMS post-build hook and, on systems that have them, the
+/// APT/pacman hooks - so modules get re-signed automatically the moment a
+/// new kernel's modules are built, instead of silently failing to load at
+/// next boot.
+pub fn install_hook(config: &Config) -> Result<()> {
+    system::check_root()?;
+
+    if config.passphrase_file.is_none() {
+        log::warn!(
+            "passphrase_file is not set in config; the hook will run \
+             non-interactively and fail to sign until one is configured"
+        );
+    }
+
+    let bin = resolve_self_path()?;
+
+    write_hook_script(&dkms_hook_path(), &dkms_hook_script(&bin))?;
+
+    if system::command_exists("apt-get") {
+        write_hook_script(&PathBuf::from(APT_HOOK_PATH), &apt_hook_script(&bin))?;
+    }
+
+    if system::command_exists("pacman") {
+        write_hook_script(&PathBuf::from(PACMAN_HOOK_PATH), &pacman_hook_script(&bin))?;
+    }
+
+    Ok(())
+}
+
+/// Removes whichever hooks `install_hook` previously installed. Safe to run
+/// even if no hook was ever installed, or if only some of them were (e.g.
+/// only the DKMS hook, on a non-APT/pacman system).
+pub fn uninstall_hook() -> Result<()> {
+    system::check_root()?;
+
+    remove_hook_script(&dkms_hook_path())?;
+    remove_hook_script(&PathBuf::from(APT_HOOK_PATH))?;
+    remove_hook_script(&PathBuf::from(PACMAN_HOOK_PATH))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hook_scripts_contain_marker_and_binary() {
+        let bin = PathBuf::from("/usr/local/bin/virtualbox-sb-manager");
+
+        assert!(dkms_hook_script(&bin).contains(HOOK_MARKER));
+        assert!(dkms_hook_script(&bin).contains("sign --target all"));
+        assert!(apt_hook_script(&bin).contains("DPkg::Post-Invoke"));
+        assert!(pacman_hook_script(&bin).contains("When = PostTransaction"));
+    }
+
+    #[test]
+    fn test_remove_hook_script_leaves_foreign_file_alone() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("not-ours.sh");
+        fs::write(&path, "#!/bin/sh\necho hi\n").unwrap();
+
+        remove_hook_script(&path).unwrap();
+
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_remove_hook_script_removes_marked_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("ours.sh");
+        fs::write(&path, dkms_hook_script(&PathBuf::from("/usr/bin/virtualbox-sb-manager"))).unwrap();
+
+        remove_hook_script(&path).unwrap();
+
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_remove_hook_script_missing_file_is_ok() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("never-existed.sh");
+
+        assert!(remove_hook_script(&path).is_ok());
+    }
+}
@@ -0,0 +1,108 @@
+//! Systemd unit for re-signing and reloading modules on boot.
+//!
+//! VirtualBox's own `vboxdrv.sh`/`vboxadd.sh` init scripts are ordered with
+//! `X-Start-Before: display-manager` so modules are rebuilt and loaded
+//! before the GUI comes up. `install_service` is the systemd equivalent: a
+//! oneshot unit that runs `vbox-sb-manager full` ahead of
+//! `display-manager.service`, plus the same DKMS post-build hook
+//! `install_hook_command` installs, so a kernel upgrade is caught both at
+//! build time and at next boot.
+
+use crate::config::Config;
+use crate::error::{Result, VBoxError};
+use crate::modules::hook::{self, resolve_self_path};
+use crate::utils::system;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the unit file is installed
+const SERVICE_UNIT_PATH: &str = "/etc/systemd/system/vbox-sb-manager.service";
+
+/// The unit this tool manages
+const SERVICE_NAME: &str = "vbox-sb-manager.service";
+
+fn service_unit(bin: &Path, config: &Config) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Re-sign and load VirtualBox kernel modules for Secure Boot\n\
+         Before=display-manager.service\n\
+         ConditionPathExists={public_key}\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         RemainAfterExit=yes\n\
+         ExecStart={bin} full\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        public_key = config.public_key.display(),
+        bin = bin.display(),
+    )
+}
+
+/// Installs `vbox-sb-manager.service`, enables it via `systemctl enable`,
+/// and installs the companion kernel-upgrade hook (see
+/// [`crate::modules::hook::install_hook`]) so modules are also re-signed
+/// the moment DKMS rebuilds them, not just at next boot.
+pub fn install_service(config: &Config) -> Result<()> {
+    system::check_root()?;
+
+    if !system::command_exists("systemctl") {
+        return Err(VBoxError::DependencyMissing("systemd".to_string()));
+    }
+
+    let bin = resolve_self_path()?;
+    let unit_path = PathBuf::from(SERVICE_UNIT_PATH);
+
+    fs::write(&unit_path, service_unit(&bin, config))
+        .map_err(|e| VBoxError::Other(format!("Failed to write {}: {}", unit_path.display(), e)))?;
+    log::info!("Installed systemd unit: {}", unit_path.display());
+
+    system::execute_command_checked("systemctl", &["daemon-reload"])?;
+    system::execute_command_checked("systemctl", &["enable", SERVICE_NAME])?;
+
+    hook::install_hook(config)?;
+
+    Ok(())
+}
+
+/// Disables and removes `vbox-sb-manager.service` and its companion hook.
+/// Safe to run even if `install_service` never ran.
+pub fn uninstall_service() -> Result<()> {
+    system::check_root()?;
+
+    if system::command_exists("systemctl") {
+        let _ = system::execute_command_checked("systemctl", &["disable", SERVICE_NAME]);
+    }
+
+    let unit_path = PathBuf::from(SERVICE_UNIT_PATH);
+    if unit_path.exists() {
+        fs::remove_file(&unit_path)
+            .map_err(|e| VBoxError::Other(format!("Failed to remove {}: {}", unit_path.display(), e)))?;
+        log::info!("Removed systemd unit: {}", unit_path.display());
+    }
+
+    if system::command_exists("systemctl") {
+        system::execute_command_checked("systemctl", &["daemon-reload"])?;
+    }
+
+    hook::uninstall_hook()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_unit_is_oneshot_before_display_manager() {
+        let config = Config::default();
+        let unit = service_unit(&PathBuf::from("/usr/local/bin/vbox-sb-manager"), &config);
+
+        assert!(unit.contains("Type=oneshot"));
+        assert!(unit.contains("Before=display-manager.service"));
+        assert!(unit.contains("ExecStart=/usr/local/bin/vbox-sb-manager full"));
+        assert!(unit.contains(&format!("ConditionPathExists={}", config.public_key.display())));
+    }
+}
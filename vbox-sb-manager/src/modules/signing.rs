@@ -2,7 +2,11 @@
 
 use crate::config::{Config, SystemPaths};
 use crate::error::{Result, VBoxError};
+use crate::modules::target::ModuleTarget;
+use crate::modules::{compression, toolchain};
+use crate::utils::logfile::OpLog;
 use crate::utils::system;
+use serde::Serialize;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
@@ -22,16 +26,35 @@ pub enum CompressionType {
     Zst,
 }
 
-/// Find all VirtualBox kernel modules
-pub fn find_vbox_modules() -> Result<Vec<ModuleInfo>> {
-    log::info!("Locating VirtualBox kernel modules...");
-    
-    let module_dir = SystemPaths::vbox_module_dir()?;
+/// Find all kernel modules belonging to `target`, by filename prefix. When
+/// `target.modules` is non-empty (the built-in VirtualBox target, narrowed
+/// by [`Config::autodetect_modules`]), a discovered module's exact name
+/// must also appear there, so a host install doesn't pick up Guest
+/// Additions modules or vice versa.
+pub fn find_modules(target: &ModuleTarget, config: &Config) -> Result<Vec<ModuleInfo>> {
+    let module_dir = SystemPaths::module_dir_for(target, config)?;
+    find_modules_in(target, &module_dir)
+}
+
+/// Same as [`find_modules`], but for `kernel_version` rather than the
+/// running kernel - modules built for a just-installed kernel, before it's
+/// been booted into, live under that kernel's own module directory.
+pub fn find_modules_for_kernel(
+    target: &ModuleTarget,
+    kernel_version: &str,
+    config: &Config,
+) -> Result<Vec<ModuleInfo>> {
+    let module_dir = SystemPaths::module_dir_for_kernel(target, kernel_version, config)?;
+    find_modules_in(target, &module_dir)
+}
+
+fn find_modules_in(target: &ModuleTarget, module_dir: &Path) -> Result<Vec<ModuleInfo>> {
+    log::info!("Locating {} kernel modules...", target.display_name);
     log::info!("Module directory: {}", module_dir.display());
-    
+
     let mut modules = Vec::new();
-    
-    for entry in WalkDir::new(&module_dir)
+
+    for entry in WalkDir::new(module_dir)
         .follow_links(true)
         .into_iter()
         .filter_map(|e| e.ok())
@@ -40,11 +63,18 @@ pub fn find_vbox_modules() -> Result<Vec<ModuleInfo>> {
         let filename = path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("");
-        
-        if filename.starts_with("vbox") && is_module_file(filename) {
-            let (compression_type, compressed) = detect_compression(filename);
+
+        if is_module_file(filename) {
             let name = extract_module_name(filename);
-            
+            if !name.starts_with(&target.filename_prefix) {
+                continue;
+            }
+            if !target.modules.is_empty() && !target.modules.iter().any(|m| m == &name) {
+                continue;
+            }
+
+            let (compression_type, compressed) = detect_compression(filename);
+
             modules.push(ModuleInfo {
                 path: path.to_path_buf(),
                 name,
@@ -53,19 +83,21 @@ pub fn find_vbox_modules() -> Result<Vec<ModuleInfo>> {
             });
         }
     }
-    
+
     if modules.is_empty() {
         return Err(VBoxError::ModuleNotFound(format!(
-            "No VirtualBox modules found in {}",
+            "No {} modules (prefix '{}') were found in {}",
+            target.display_name,
+            target.filename_prefix,
             module_dir.display()
         )));
     }
-    
-    log::info!("Found {} VirtualBox module(s)", modules.len());
+
+    log::info!("Found {} {} module(s)", modules.len(), target.display_name);
     for module in &modules {
         log::debug!("  - {} at {}", module.name, module.path.display());
     }
-    
+
     Ok(modules)
 }
 
@@ -105,155 +137,256 @@ fn decompress_module(module: &ModuleInfo) -> Result<PathBuf> {
     if !module.compressed {
         return Ok(module.path.clone());
     }
-    
+
     log::info!("Decompressing {}...", module.path.display());
-    
-    let decompressed_path = module.path.with_extension("");
-    
-    match module.compression_type {
-        Some(CompressionType::Xz) => {
-            system::execute_command_checked("xz", &["-dk", module.path.to_str().unwrap()])?;
-        }
-        Some(CompressionType::Gz) => {
-            system::execute_command_checked("gunzip", &["-k", module.path.to_str().unwrap()])?;
-        }
-        Some(CompressionType::Zst) => {
-            system::execute_command_checked("zstd", &["-dkfq", module.path.to_str().unwrap()])?;
-        }
-        None => {}
-    }
-    
-    Ok(decompressed_path)
+
+    let compression_type = module
+        .compression_type
+        .as_ref()
+        .ok_or_else(|| VBoxError::Other("compressed module has no compression type".to_string()))?;
+
+    compression::decompress(&module.path, compression_type)
 }
 
 /// Recompress a module file
 fn recompress_module(decompressed_path: &Path, compression_type: CompressionType) -> Result<()> {
     log::info!("Recompressing {}...", decompressed_path.display());
-    
-    match compression_type {
-        CompressionType::Xz => {
-            system::execute_command_checked("xz", &["-f", decompressed_path.to_str().unwrap()])?;
-        }
-        CompressionType::Gz => {
-            system::execute_command_checked("gzip", &["-f", decompressed_path.to_str().unwrap()])?;
-        }
-        CompressionType::Zst => {
-            system::execute_command_checked(
-                "zstd",
-                &["-qf", "--rm", decompressed_path.to_str().unwrap()],
-            )?;
-        }
-    }
-    
+
+    compression::recompress(decompressed_path, &compression_type)?;
+
     Ok(())
 }
 
 /// Sign a single module
-pub fn sign_module(module: &ModuleInfo, config: &Config, passphrase: &str) -> Result<()> {
+pub fn sign_module(
+    module: &ModuleInfo,
+    config: &Config,
+    passphrase: &str,
+    oplog: Option<&OpLog>,
+) -> Result<()> {
     log::info!("Signing module: {}...", module.path.display());
-    
+
     // Find sign-file tool
     let sign_file_tool = SystemPaths::find_sign_file_tool()?;
     log::debug!("Using sign-file tool: {}", sign_file_tool.display());
-    
+
     // Decompress if needed
     let module_to_sign = decompress_module(module)?;
-    
+
     // Set passphrase environment variable
     std::env::set_var("KBUILD_SIGN_PIN", passphrase);
-    
+
     // Sign the module
-    let result = system::execute_command_checked(
-        sign_file_tool.to_str().unwrap(),
-        &[
-            &config.hash_algo,
-            config.private_key.to_str().unwrap(),
-            config.public_key.to_str().unwrap(),
-            module_to_sign.to_str().unwrap(),
-        ],
-    );
-    
+    let sign_args = [
+        config.hash_algo.as_str(),
+        config.private_key.to_str().unwrap(),
+        config.public_key.to_str().unwrap(),
+        module_to_sign.to_str().unwrap(),
+    ];
+    let result = match oplog {
+        Some(log_file) => log_file
+            .run_and_capture(sign_file_tool.to_str().unwrap(), &sign_args)
+            .and_then(|output| {
+                if output.status.success() {
+                    Ok(output)
+                } else {
+                    Err(VBoxError::CommandFailed(format!(
+                        "sign-file failed: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                    )))
+                }
+            }),
+        None => system::execute_command_checked(sign_file_tool.to_str().unwrap(), &sign_args),
+    };
+
     // Clear passphrase from environment
     std::env::remove_var("KBUILD_SIGN_PIN");
-    
+
     result?;
-    
+
     // Recompress if it was compressed
     if module.compressed {
         if let Some(ref compression_type) = module.compression_type {
             recompress_module(&module_to_sign, compression_type.clone())?;
         }
     }
-    
+
     log::info!("Successfully signed: {}", module.name);
     Ok(())
 }
 
-/// Sign all VirtualBox modules
-pub fn sign_all_modules(config: &Config, passphrase: &str) -> Result<()> {
+/// Outcome of signing one kernel's worth of `target`'s modules - the unit
+/// [`sign_all_modules_for_kernels_detailed`] reports, and what `sign
+/// --format json` serializes.
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelSignOutcome {
+    pub kernel_version: String,
+    pub signed: usize,
+    pub failed: usize,
+    /// Set instead of `signed`/`failed` when modules for this kernel
+    /// couldn't even be enumerated (e.g. no DKMS build for it yet)
+    pub error: Option<String>,
+}
+
+impl KernelSignOutcome {
+    fn is_ok(&self) -> bool {
+        self.error.is_none() && self.failed == 0
+    }
+}
+
+/// Sign every module in `target`, for the running kernel only
+pub fn sign_all_modules(
+    target: &ModuleTarget,
+    config: &Config,
+    passphrase: &str,
+    oplog: Option<&OpLog>,
+) -> Result<()> {
+    let kernel_version = SystemPaths::kernel_version()?;
+    sign_all_modules_for_kernels(target, config, passphrase, &[kernel_version], oplog)
+}
+
+/// Sign every module in `target`, across every kernel version in
+/// `kernel_versions` - the running kernel, one explicitly named kernel, or
+/// every kernel under `/lib/modules` (see `--kernel`/`--all-kernels` on the
+/// `sign`/`full` commands). Convenience wrapper around
+/// [`sign_all_modules_for_kernels_detailed`] for callers that just want a
+/// pass/fail result.
+pub fn sign_all_modules_for_kernels(
+    target: &ModuleTarget,
+    config: &Config,
+    passphrase: &str,
+    kernel_versions: &[String],
+    oplog: Option<&OpLog>,
+) -> Result<()> {
+    let outcomes =
+        sign_all_modules_for_kernels_detailed(target, config, passphrase, kernel_versions, oplog)?;
+    sign_outcomes_to_result(target, &outcomes)
+}
+
+/// Sign every module in `target` across `kernel_versions`, returning a
+/// per-kernel [`KernelSignOutcome`] instead of collapsing straight to
+/// pass/fail - this is what a `--format json` caller serializes. Reports
+/// progress through [`crate::utils::output::print_progress`] and keeps
+/// going across kernels after a failure, the same way `--target all` keeps
+/// going across targets.
+pub fn sign_all_modules_for_kernels_detailed(
+    target: &ModuleTarget,
+    config: &Config,
+    passphrase: &str,
+    kernel_versions: &[String],
+    oplog: Option<&OpLog>,
+) -> Result<Vec<KernelSignOutcome>> {
     system::check_root()?;
-    log::info!("Starting VirtualBox module signing process...");
-    
+    log::info!("Starting {} module signing process...", target.display_name);
+
     // Verify keys exist
     if !config.keys_exist() {
         return Err(VBoxError::KeyNotFound(
             "Signing keys not found. Run 'setup' command first.".to_string(),
         ));
     }
-    
-    // Find modules
-    let modules = find_vbox_modules()?;
-    
+
+    let mut outcomes = Vec::new();
+
+    for (index, kernel_version) in kernel_versions.iter().enumerate() {
+        crate::utils::output::print_progress(
+            index + 1,
+            kernel_versions.len(),
+            &format!("Signing {} modules for kernel {}", target.display_name, kernel_version),
+        );
+
+        let outcome = match sign_modules_for_one_kernel(target, config, passphrase, kernel_version, oplog) {
+            Ok((signed, failed)) => KernelSignOutcome {
+                kernel_version: kernel_version.clone(),
+                signed,
+                failed,
+                error: None,
+            },
+            Err(e) => KernelSignOutcome {
+                kernel_version: kernel_version.clone(),
+                signed: 0,
+                failed: 0,
+                error: Some(e.to_string()),
+            },
+        };
+
+        if outcome.is_ok() {
+            log::info!("Kernel {}: {} module(s) signed", kernel_version, outcome.signed);
+        } else {
+            log::error!(
+                "Kernel {}: {} signed, {} failed{}",
+                kernel_version,
+                outcome.signed,
+                outcome.failed,
+                outcome
+                    .error
+                    .as_ref()
+                    .map(|e| format!(" ({})", e))
+                    .unwrap_or_default()
+            );
+        }
+
+        outcomes.push(outcome);
+    }
+
+    Ok(outcomes)
+}
+
+/// Reduces a set of [`KernelSignOutcome`]s to a single pass/fail [`Result`]
+pub fn sign_outcomes_to_result(target: &ModuleTarget, outcomes: &[KernelSignOutcome]) -> Result<()> {
+    let failed_kernels: Vec<&str> = outcomes
+        .iter()
+        .filter(|o| !o.is_ok())
+        .map(|o| o.kernel_version.as_str())
+        .collect();
+
+    if failed_kernels.is_empty() {
+        log::info!("All modules signed successfully across {} kernel(s)!", outcomes.len());
+        Ok(())
+    } else {
+        Err(VBoxError::Other(format!(
+            "failed to sign {} modules for kernel(s): {}",
+            target.display_name,
+            failed_kernels.join(", ")
+        )))
+    }
+}
+
+/// Signs one kernel's worth of `target`'s modules, returning `(signed, failed)`
+fn sign_modules_for_one_kernel(
+    target: &ModuleTarget,
+    config: &Config,
+    passphrase: &str,
+    kernel_version: &str,
+    oplog: Option<&OpLog>,
+) -> Result<(usize, usize)> {
+    let modules = find_modules_for_kernel(target, kernel_version, config)?;
+
     let mut signed_count = 0;
     let mut failed_count = 0;
-    
+
     for module in &modules {
-        match sign_module(module, config, passphrase) {
+        match sign_module(module, config, passphrase, oplog) {
             Ok(_) => signed_count += 1,
             Err(e) => {
-                log::error!("Failed to sign {}: {}", module.name, e);
+                log::error!("Failed to sign {} (kernel {}): {}", module.name, kernel_version, e);
                 failed_count += 1;
             }
         }
     }
-    
-    log::info!(
-        "Signing complete: {} successful, {} failed",
-        signed_count,
-        failed_count
-    );
-    
-    if failed_count > 0 {
-        Err(VBoxError::Other(format!(
-            "{} module(s) failed to sign",
-            failed_count
-        )))
-    } else {
-        log::info!("All modules signed successfully!");
-        Ok(())
-    }
+
+    Ok((signed_count, failed_count))
 }
 
-/// Rebuild VirtualBox modules via DKMS
-pub fn rebuild_vbox_modules() -> Result<()> {
+/// Rebuild the modules in `target` via DKMS
+pub fn rebuild_modules(target: &ModuleTarget, config: &Config, oplog: Option<&OpLog>) -> Result<()> {
     system::check_root()?;
-    log::info!("Rebuilding VirtualBox kernel modules via DKMS...");
-    
-    // Check if virtualbox-dkms is installed
-    let dpkg_output = system::execute_command("dpkg", &["-l"])?;
-    let dpkg_stdout = String::from_utf8_lossy(&dpkg_output.stdout);
-    
-    if !dpkg_stdout.contains("virtualbox-dkms") {
-        log::warn!("virtualbox-dkms package not found");
-        return Err(VBoxError::DkmsBuildFailed(
-            "virtualbox-dkms is not installed".to_string(),
-        ));
-    }
-    
-    // Find VirtualBox DKMS version
-    let dkms_output = system::execute_command("dkms", &["status", "virtualbox"])?;
+    log::info!("Rebuilding {} kernel modules via DKMS...", target.display_name);
+
+    // Find the DKMS version for this target's package
+    let dkms_output = system::execute_command("dkms", &["status", &target.dkms_package])?;
     let dkms_stdout = String::from_utf8_lossy(&dkms_output.stdout);
-    
+
     let version = dkms_stdout
         .lines()
         .next()
@@ -263,33 +396,59 @@ pub fn rebuild_vbox_modules() -> Result<()> {
                 .and_then(|s| s.split('/').nth(1))
         })
         .ok_or_else(|| {
-            VBoxError::DkmsBuildFailed("Could not determine VirtualBox DKMS version".to_string())
+            VBoxError::DkmsBuildFailed(format!(
+                "Could not determine DKMS version for package '{}'",
+                target.dkms_package
+            ))
         })?;
-    
-    log::info!("Found VirtualBox DKMS version: {}", version);
-    
+
+    log::info!("Found {} DKMS version: {}", target.dkms_package, version);
+
     let kernel_version = SystemPaths::kernel_version()?;
-    
-    // Unload modules if loaded
-    log::info!("Unloading existing VirtualBox modules...");
-    for module in ["vboxnetadp", "vboxnetflt", "vboxdrv"].iter() {
-        system::unload_module(module)?;
+
+    // Unload modules if loaded, in reverse of the target's load order. A
+    // target auto-detected from `dkms status` alone doesn't know its module
+    // names yet, so there's nothing to unload up front.
+    if target.modules.is_empty() {
+        log::warn!(
+            "{} has no known module names; skipping unload before rebuild",
+            target.display_name
+        );
+    } else {
+        log::info!("Unloading existing {} modules...", target.display_name);
+        for module in target.modules.iter().rev() {
+            system::unload_module(module)?;
+        }
     }
-    
+
+    // Match the build toolchain to the one the running kernel was built
+    // with, so a clang-built kernel doesn't get a gcc-built .ko.
+    let detected_toolchain = toolchain::detect_toolchain()?;
+    toolchain::apply_build_env(&detected_toolchain);
+
+    // Match the release/debug build type vboxdrv.sh recorded in
+    // /etc/vbox/vbox.cfg, so a debug install doesn't get rebuilt as release
+    // (or vice versa).
+    log::info!("Building with KBUILD_TYPE={}", config.build_type);
+    std::env::set_var("KBUILD_TYPE", &config.build_type);
+
     // Force rebuild
     log::info!("Forcing DKMS rebuild (this may take a minute)...");
-    system::execute_command_checked(
-        "dkms",
-        &[
-            "install",
-            &format!("virtualbox/{}", version),
-            "-k",
-            &kernel_version,
-            "--force",
-        ],
-    )?;
-    
-    log::info!("VirtualBox modules rebuilt successfully");
+    let dkms_package = format!("{}/{}", target.dkms_package, version);
+    let dkms_args = ["install", dkms_package.as_str(), "-k", kernel_version.as_str(), "--force"];
+    let rebuild_output = match oplog {
+        Some(log_file) => log_file.run_and_capture("dkms", &dkms_args),
+        None => system::execute_command("dkms", &dkms_args),
+    }?;
+
+    if !rebuild_output.status.success() {
+        return Err(VBoxError::DkmsBuildFailed(format!(
+            "dkms install failed: {}",
+            String::from_utf8_lossy(&rebuild_output.stderr)
+        )));
+    }
+
+    log::info!("{} modules rebuilt successfully", target.display_name);
     Ok(())
 }
 
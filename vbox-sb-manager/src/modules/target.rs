@@ -0,0 +1,150 @@
+//! Describes a DKMS-managed set of kernel modules (VirtualBox, NVIDIA,
+//! zfs, v4l2loopback, ...) so the signing engine isn't hardwired to
+//! VirtualBox. Everything in `modules::signing` and `modules::verification`
+//! that used to assume "the VirtualBox modules" now takes a [`ModuleTarget`]
+//! instead.
+
+use crate::config::Config;
+use crate::error::{Result, VBoxError};
+use crate::utils::system;
+use std::collections::HashSet;
+
+/// A set of kernel modules built, signed, and loaded together under one
+/// DKMS package, e.g. VirtualBox's `vboxdrv`/`vboxnetflt`/`vboxnetadp`.
+#[derive(Debug, Clone)]
+pub struct ModuleTarget {
+    /// Human-readable name shown in output (e.g. "VirtualBox")
+    pub display_name: String,
+
+    /// Filename prefix used to recognize this target's `.ko` files
+    pub filename_prefix: String,
+
+    /// DKMS package name, the part before `/<version>` in `dkms status`
+    pub dkms_package: String,
+
+    /// Kernel modules belonging to this target, in load order (unload
+    /// happens in reverse order). Empty for a target that was
+    /// auto-detected from `dkms status` alone, which doesn't report
+    /// module names.
+    pub modules: Vec<String>,
+}
+
+impl ModuleTarget {
+    /// The built-in VirtualBox target, using whatever module set `config`
+    /// has (narrowed by [`Config::autodetect_modules`])
+    pub fn vbox(config: &Config) -> Self {
+        Self {
+            display_name: "VirtualBox".to_string(),
+            filename_prefix: "vbox".to_string(),
+            dkms_package: "virtualbox".to_string(),
+            modules: config.modules.clone(),
+        }
+    }
+
+    /// Resolves a `--target` argument to a [`ModuleTarget`]: the built-in
+    /// VirtualBox target for "virtualbox", or a lookup among whatever
+    /// `dkms status` reports otherwise.
+    pub fn find(name: &str, config: &Config) -> Result<Self> {
+        if name.eq_ignore_ascii_case("virtualbox") {
+            return Ok(Self::vbox(config));
+        }
+
+        Self::autodetect_all(config)?
+            .into_iter()
+            .find(|target| target.dkms_package.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                VBoxError::ModuleNotFound(format!(
+                    "no DKMS-managed package named '{}' (see `dkms status`)",
+                    name
+                ))
+            })
+    }
+
+    /// Enumerates every DKMS-managed package on this system as a target, by
+    /// parsing `dkms status`. The VirtualBox package always comes back as
+    /// the built-in [`Self::vbox`] target, since "virtualbox" (the DKMS
+    /// package name) is not a module filename prefix - `vboxdrv.ko` et al.
+    /// would never match it in `find_modules_in`. Every other package's
+    /// `modules` list starts empty and its `filename_prefix` is guessed
+    /// from the package name (see [`Self::guess_filename_prefix`]) -
+    /// `modules::signing::find_modules` discovers the actual module files
+    /// by that prefix.
+    pub fn autodetect_all(config: &Config) -> Result<Vec<Self>> {
+        let output = system::execute_command_output("dkms", &["status"])?;
+
+        let mut seen = HashSet::new();
+        let mut targets = Vec::new();
+
+        for line in output.lines() {
+            let Some(package) = line.split(',').next().and_then(|s| s.split('/').next()) else {
+                continue;
+            };
+            let package = package.trim();
+
+            if package.is_empty() || !seen.insert(package.to_string()) {
+                continue;
+            }
+
+            if package.eq_ignore_ascii_case("virtualbox") {
+                targets.push(Self::vbox(config));
+                continue;
+            }
+
+            targets.push(Self {
+                display_name: package.to_string(),
+                filename_prefix: Self::guess_filename_prefix(package),
+                dkms_package: package.to_string(),
+                modules: Vec::new(),
+            });
+        }
+
+        if targets.is_empty() {
+            return Err(VBoxError::ModuleNotFound(
+                "no DKMS-managed modules found (`dkms status` returned none)".to_string(),
+            ));
+        }
+
+        Ok(targets)
+    }
+
+    /// Guesses the `.ko` filename prefix for an auto-detected DKMS package
+    /// from its package name alone, since `dkms status` never reports
+    /// module names. Most DKMS packages are named `<module>-dkms`
+    /// (`v4l2loopback-dkms` building `v4l2loopback.ko`,
+    /// `zfs-dkms` building `zfs.ko`), so stripping that suffix is right far
+    /// more often than assuming the package name itself is the prefix.
+    fn guess_filename_prefix(package: &str) -> String {
+        package.strip_suffix("-dkms").unwrap_or(package).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vbox_target_uses_config_modules() {
+        let config = Config::default();
+        let target = ModuleTarget::vbox(&config);
+        assert_eq!(target.modules, config.modules);
+        assert_eq!(target.dkms_package, "virtualbox");
+    }
+
+    #[test]
+    fn test_guess_filename_prefix_strips_dkms_suffix() {
+        assert_eq!(ModuleTarget::guess_filename_prefix("v4l2loopback-dkms"), "v4l2loopback");
+        assert_eq!(ModuleTarget::guess_filename_prefix("zfs-dkms"), "zfs");
+    }
+
+    #[test]
+    fn test_guess_filename_prefix_falls_back_to_package_name() {
+        assert_eq!(ModuleTarget::guess_filename_prefix("nvidia"), "nvidia");
+    }
+
+    #[test]
+    fn test_find_virtualbox_is_case_insensitive() {
+        let config = Config::default();
+        let target = ModuleTarget::find("VirtualBox", &config).unwrap();
+        assert_eq!(target.dkms_package, "virtualbox");
+    }
+}
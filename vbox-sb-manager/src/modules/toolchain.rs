@@ -0,0 +1,128 @@
+//! Kernel build-toolchain detection (gcc vs. clang/LLVM).
+//!
+//! Building an out-of-tree module with a compiler that doesn't match the one
+//! the running kernel was built with can fail outright, or produce a module
+//! that loads but behaves unpredictably. `vboxdrv.sh` works around this by
+//! detecting a clang-built kernel and exporting `LLVM=1`/`CC=clang` before
+//! invoking the DKMS build; we do the same here.
+
+use crate::config::SystemPaths;
+use crate::error::Result;
+use crate::utils::system;
+use std::io::Read;
+
+/// Compiler the running kernel was built with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Toolchain {
+    Gcc,
+    Clang,
+}
+
+impl std::fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Toolchain::Gcc => write!(f, "gcc"),
+            Toolchain::Clang => write!(f, "clang/LLVM"),
+        }
+    }
+}
+
+/// Detects the toolchain the running kernel was built with.
+///
+/// Tries, in order: `/proc/version` (carries a `clang version` string on
+/// clang-built kernels), `CONFIG_CC_IS_CLANG` in the matching
+/// `/lib/modules/$(uname -r)/build/.config`, and finally the same key in
+/// `/proc/config.gz`. Defaults to `Gcc` when nothing indicates clang, since
+/// that's the overwhelmingly common case and we'd rather under- than
+/// over-detect a toolchain mismatch.
+pub fn detect_toolchain() -> Result<Toolchain> {
+    if let Ok(version) = std::fs::read_to_string("/proc/version") {
+        if version.contains("clang version") {
+            log::debug!("/proc/version indicates a clang-built kernel");
+            return Ok(Toolchain::Clang);
+        }
+    }
+
+    if let Ok(kernel_version) = SystemPaths::kernel_version() {
+        let config_path =
+            format!("/lib/modules/{}/build/.config", kernel_version);
+        if let Ok(config) = std::fs::read_to_string(&config_path) {
+            if config_is_clang(&config) {
+                log::debug!("{} sets CONFIG_CC_IS_CLANG=y", config_path);
+                return Ok(Toolchain::Clang);
+            }
+            return Ok(Toolchain::Gcc);
+        }
+    }
+
+    if let Some(config) = read_proc_config_gz()? {
+        if config_is_clang(&config) {
+            log::debug!("/proc/config.gz sets CONFIG_CC_IS_CLANG=y");
+            return Ok(Toolchain::Clang);
+        }
+    }
+
+    Ok(Toolchain::Gcc)
+}
+
+fn config_is_clang(config: &str) -> bool {
+    config
+        .lines()
+        .any(|line| line.trim() == "CONFIG_CC_IS_CLANG=y")
+}
+
+/// Reads and decompresses `/proc/config.gz`, if the running kernel exposes it
+fn read_proc_config_gz() -> Result<Option<String>> {
+    use flate2::read::GzDecoder;
+
+    let path = std::path::Path::new("/proc/config.gz");
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file = std::fs::File::open(path)?;
+    let mut decoder = GzDecoder::new(file);
+    let mut contents = String::new();
+    if decoder.read_to_string(&mut contents).is_err() {
+        return Ok(None);
+    }
+
+    Ok(Some(contents))
+}
+
+/// Exports the environment variables DKMS/kbuild expect when building
+/// against a clang-compiled kernel. A no-op for `Toolchain::Gcc`.
+pub fn apply_build_env(toolchain: &Toolchain) {
+    match toolchain {
+        Toolchain::Clang => {
+            log::info!("Clang-built kernel detected; building with LLVM=1 CC=clang");
+            std::env::set_var("LLVM", "1");
+            std::env::set_var("CC", "clang");
+            std::env::set_var("HOSTCC", "clang");
+        }
+        Toolchain::Gcc => {}
+    }
+}
+
+/// Human-readable toolchain summary for `status_command`, noting whether
+/// clang is actually installed when it's required.
+pub fn describe_toolchain() -> String {
+    match detect_toolchain() {
+        Ok(Toolchain::Clang) if !system::command_exists("clang") => {
+            "clang/LLVM (required, but clang is not installed)".to_string()
+        }
+        Ok(toolchain) => toolchain.to_string(),
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_is_clang() {
+        assert!(config_is_clang("CONFIG_FOO=y\nCONFIG_CC_IS_CLANG=y\n"));
+        assert!(!config_is_clang("CONFIG_FOO=y\nCONFIG_CC_IS_GCC=y\n"));
+    }
+}
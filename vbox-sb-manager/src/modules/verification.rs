@@ -1,112 +1,489 @@
 //! Module verification and loading
 
+use crate::config::Config;
 use crate::error::{Result, VBoxError};
-use crate::modules::signing::{find_vbox_modules, CompressionType, ModuleInfo};
+use crate::modules::compression;
+use crate::modules::signing::{find_modules_for_kernel, ModuleInfo};
+use crate::modules::target::ModuleTarget;
 use crate::utils::system;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
 
-/// Verify a single module signature
-pub fn verify_module_signature(module: &ModuleInfo) -> Result<bool> {
+/// Trailer the kernel appends to a signed `.ko` (see `module_signature.h`)
+const SIG_MAGIC: &[u8] = b"~Module signature appended~\n";
+
+/// Size in bytes of the `module_signature` struct that precedes [`SIG_MAGIC`]
+const SIG_FOOTER_LEN: usize = 12;
+
+/// Outcome of checking a module's appended signature against our MOK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// No appended PKCS#7 signature found
+    Unsigned,
+    /// Signed, but by a certificate other than `config.public_key`
+    SignedByOtherKey,
+    /// Signed by the certificate in `config.public_key`
+    Signed,
+}
+
+impl SignatureStatus {
+    /// True only when the module is signed by our own enrolled MOK
+    pub fn is_trusted(self) -> bool {
+        matches!(self, SignatureStatus::Signed)
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SignatureStatus::Unsigned => "unsigned",
+            SignatureStatus::SignedByOtherKey => "signed (other key)",
+            SignatureStatus::Signed => "signed (our MOK)",
+        }
+    }
+}
+
+impl std::fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Verify a single module's appended signature against the configured MOK
+pub fn verify_module_signature(module: &ModuleInfo, config: &Config) -> Result<SignatureStatus> {
+    inspect_module_signature(module, config).map(|details| details.status)
+}
+
+/// Full detail behind a [`SignatureStatus`] - the signer's Subject Key
+/// Identifier and the hash algorithm the kernel recorded in the
+/// `module_signature` footer - so `verify` can report trustworthy evidence
+/// ("signed by other key, sha256, SKID ...") instead of a bare yes/no.
+#[derive(Debug, Clone)]
+pub struct SignatureDetails {
+    pub status: SignatureStatus,
+    pub signer_skid: Option<String>,
+    pub hash_algo: Option<&'static str>,
+}
+
+/// Same as [`verify_module_signature`], but returns the full
+/// [`SignatureDetails`] instead of collapsing straight to a [`SignatureStatus`]
+pub fn inspect_module_signature(module: &ModuleInfo, config: &Config) -> Result<SignatureDetails> {
     let module_path = if module.compressed {
-        // Decompress temporarily for verification
-        let decompressed = decompress_for_verification(module)?;
-        decompressed
+        decompress_for_verification(module)?
     } else {
         module.path.clone()
     };
-    
-    // Use modinfo to check for signature
-    let output = system::execute_command(
-        "modinfo",
-        &[module_path.to_str().unwrap()],
-    )?;
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let is_signed = stdout.contains("sig_id:") || stdout.contains("signer:");
-    
+
+    let details = inspect_appended_signature(&module_path, config);
+
     // Clean up decompressed file if we created it
     if module.compressed {
         let _ = std::fs::remove_file(&module_path);
     }
-    
-    Ok(is_signed)
+
+    details
+}
+
+/// Maps a kernel `enum pkey_hash_algo` id (see `linux/include/crypto/pkcs7.h`)
+/// to its name, for the handful of algorithms `sign-file` actually produces
+fn hash_algo_name(id: u8) -> &'static str {
+    match id {
+        0 => "md4",
+        1 => "md5",
+        2 => "sha1",
+        3 => "rmd160",
+        4 => "sha256",
+        5 => "sha384",
+        6 => "sha512",
+        7 => "sha224",
+        _ => "unknown",
+    }
+}
+
+/// Parse the trailing `module_signature` footer and compare the embedded
+/// signer against `config.public_key`.
+fn inspect_appended_signature(module_path: &Path, config: &Config) -> Result<SignatureDetails> {
+    let data = std::fs::read(module_path)?;
+
+    if data.len() < SIG_MAGIC.len() + SIG_FOOTER_LEN || !data.ends_with(SIG_MAGIC) {
+        return Ok(SignatureDetails { status: SignatureStatus::Unsigned, signer_skid: None, hash_algo: None });
+    }
+
+    let footer_end = data.len() - SIG_MAGIC.len();
+    let footer_start = footer_end - SIG_FOOTER_LEN;
+    let footer = &data[footer_start..footer_end];
+
+    // struct module_signature { u8 algo, hash, id_type, signer_len, key_id_len;
+    //                            u8 __pad[3]; __be32 sig_len; }
+    let hash_algo = hash_algo_name(footer[1]);
+    let sig_len = u32::from_be_bytes([footer[8], footer[9], footer[10], footer[11]]) as usize;
+
+    if sig_len == 0 || sig_len > footer_start {
+        return Ok(SignatureDetails { status: SignatureStatus::Unsigned, signer_skid: None, hash_algo: None });
+    }
+
+    let der_start = footer_start - sig_len;
+    let der_blob = &data[der_start..footer_start];
+
+    let sig_tmp = write_temp_der(der_blob)?;
+    let (status, signer_skid) = compare_signer_to_mok(sig_tmp.path(), config)?;
+    // `sig_tmp` is removed on drop at the end of this function
+
+    Ok(SignatureDetails { status, signer_skid, hash_algo: Some(hash_algo) })
+}
+
+/// Writes a PKCS#7/CMS DER blob to a throwaway temp file for openssl to read.
+/// Uses a `NamedTempFile` (unique name, created with `O_EXCL`) rather than a
+/// PID-based path, since this runs as root and a predictable path in a
+/// shared temp dir could be pre-planted or symlinked by another user.
+fn write_temp_der(der_blob: &[u8]) -> Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(der_blob)?;
+    Ok(file)
+}
+
+/// Compares the signer's Subject Key Identifier against our MOK's SKID,
+/// returning the signer's SKID alongside the resulting status so callers can
+/// report who actually signed an untrusted module.
+fn compare_signer_to_mok(sig_der: &Path, config: &Config) -> Result<(SignatureStatus, Option<String>)> {
+    let signer_skid = match extract_signer_skid(sig_der) {
+        Ok(skid) => skid,
+        Err(_) => {
+            // Signature present but not a PKCS#7 blob we can parse -
+            // still "signed", just not verifiable as ours.
+            return Ok((SignatureStatus::SignedByOtherKey, None));
+        }
+    };
+
+    let our_skid = extract_cert_skid(&config.public_key)?;
+
+    if signer_skid == our_skid {
+        Ok((SignatureStatus::Signed, Some(signer_skid)))
+    } else {
+        Ok((SignatureStatus::SignedByOtherKey, Some(signer_skid)))
+    }
+}
+
+/// Extracts the Subject Key Identifier of the signer embedded in a PKCS#7 blob
+fn extract_signer_skid(sig_der: &Path) -> Result<String> {
+    let output = system::execute_command_checked(
+        "openssl",
+        &[
+            "pkcs7",
+            "-inform",
+            "DER",
+            "-in",
+            sig_der.to_str().unwrap(),
+            "-print_certs",
+            "-noout",
+        ],
+    );
+
+    // Older module signatures are a bare PKCS#7 SignedData with no
+    // certificate attached (the signer is identified by key id only);
+    // fall back to `openssl cms` which can still parse the SignerInfo.
+    let pem = match output {
+        Ok(_) => system::execute_command_output(
+            "openssl",
+            &[
+                "pkcs7",
+                "-inform",
+                "DER",
+                "-in",
+                sig_der.to_str().unwrap(),
+                "-print_certs",
+                "-text",
+            ],
+        )?,
+        Err(_) => system::execute_command_output(
+            "openssl",
+            &["cms", "-inform", "DER", "-in", sig_der.to_str().unwrap(), "-cmsout", "-noout", "-print"],
+        )?,
+    };
+
+    extract_skid_from_pem_or_text(&pem)
+}
+
+/// Extracts the Subject Key Identifier of our own enrolled certificate
+fn extract_cert_skid(public_key: &Path) -> Result<String> {
+    let output = system::execute_command_output(
+        "openssl",
+        &[
+            "x509",
+            "-inform",
+            "DER",
+            "-in",
+            public_key.to_str().unwrap(),
+            "-noout",
+            "-ext",
+            "subjectKeyIdentifier",
+        ],
+    )?;
+
+    extract_skid_from_pem_or_text(&output)
+}
+
+/// Pulls the hex SKID value out of whichever of the two shapes openssl
+/// handed us. `extract_cert_skid` always sees the first shape;
+/// `extract_signer_skid` sees the first shape when the embedded cert parses
+/// and the second - the common case for real kernel module signatures,
+/// which are a bare KeyIdentifier SignerInfo with no embedded cert - when it
+/// falls back to `openssl cms`:
+///
+///   `openssl x509 ... -ext subjectKeyIdentifier`:
+///       X509v3 Subject Key Identifier:
+///           AB:CD:EF:...
+///
+///   `openssl cms -cmsout -noout -print`:
+///       d.subjectKeyIdentifier:
+///         0000 - ab cd ef-01 ...                            ....
+fn extract_skid_from_pem_or_text(text: &str) -> Result<String> {
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        if !line.to_lowercase().replace(' ', "").contains("subjectkeyidentifier") {
+            continue;
+        }
+
+        let mut hex = String::new();
+        while let Some(next_line) = lines.peek() {
+            match hexdump_row_bytes(next_line) {
+                Some(bytes) => {
+                    hex.push_str(&bytes);
+                    lines.next();
+                }
+                None => break,
+            }
+        }
+        if !hex.is_empty() {
+            return Ok(colonify_hex(&hex));
+        }
+
+        if let Some(value) = lines.next() {
+            let value = value.trim();
+            if !value.is_empty() {
+                return Ok(value.to_uppercase());
+            }
+        }
+    }
+
+    Err(VBoxError::SignatureVerificationFailed(
+        "could not determine signer Subject Key Identifier".to_string(),
+    ))
+}
+
+/// Parses one row of an `openssl ... -print` hex dump
+/// (`"    0000 - ab cd ef-01 ...                            ...."`) into its
+/// raw hex digits, or `None` if `line` isn't such a row. The offset column
+/// and the trailing ASCII column (separated from the hex by a run of spaces)
+/// are both discarded.
+fn hexdump_row_bytes(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    let (offset, rest) = trimmed.split_once(" - ")?;
+    if offset.is_empty() || !offset.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let hex_column = rest.split("   ").next().unwrap_or(rest);
+    let hex: String = hex_column.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+
+    if hex.is_empty() {
+        None
+    } else {
+        Some(hex)
+    }
+}
+
+/// Formats a flat hex string (`"abcdef01"`) as a colon-separated SKID
+/// (`"AB:CD:EF:01"`), matching the shape `openssl x509 -ext
+/// subjectKeyIdentifier` prints, so both code paths compare equal.
+fn colonify_hex(hex: &str) -> String {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| std::str::from_utf8(pair).unwrap_or_default().to_uppercase())
+        .collect::<Vec<_>>()
+        .join(":")
 }
 
 /// Decompress a module temporarily for verification
 fn decompress_for_verification(module: &ModuleInfo) -> Result<std::path::PathBuf> {
     let decompressed_path = module.path.with_extension("");
-    
+
     // If already decompressed, just return the path
     if decompressed_path.exists() {
         return Ok(decompressed_path);
     }
-    
-    match module.compression_type {
-        Some(CompressionType::Xz) => {
-            system::execute_command_checked("xz", &["-dk", module.path.to_str().unwrap()])?;
-        }
-        Some(CompressionType::Gz) => {
-            system::execute_command_checked("gunzip", &["-k", module.path.to_str().unwrap()])?;
-        }
-        Some(CompressionType::Zst) => {
-            system::execute_command_checked("zstd", &["-dkfq", module.path.to_str().unwrap()])?;
+
+    let Some(ref compression_type) = module.compression_type else {
+        return Ok(decompressed_path);
+    };
+
+    compression::decompress(&module.path, compression_type)
+}
+
+/// Outcome of verifying one kernel's worth of `target`'s modules - the unit
+/// [`verify_all_modules_for_kernels_detailed`] reports, and what `verify
+/// --format json` serializes.
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelVerifyOutcome {
+    pub kernel_version: String,
+    pub trusted: usize,
+    pub untrusted: usize,
+    /// Set instead of `trusted`/`untrusted` when modules for this kernel
+    /// couldn't even be enumerated
+    pub error: Option<String>,
+}
+
+impl KernelVerifyOutcome {
+    fn is_ok(&self) -> bool {
+        self.error.is_none() && self.untrusted == 0
+    }
+}
+
+/// Verify every module signature in `target`, for the running kernel only
+pub fn verify_all_modules(target: &ModuleTarget, config: &Config) -> Result<()> {
+    let kernel_version = crate::config::SystemPaths::kernel_version()?;
+    verify_all_modules_for_kernels(target, config, &[kernel_version])
+}
+
+/// Verify every module signature in `target`, across every kernel version in
+/// `kernel_versions` (see `--kernel`/`--all-kernels` on the `verify`/`full`
+/// commands). Convenience wrapper around
+/// [`verify_all_modules_for_kernels_detailed`] for callers that just want a
+/// pass/fail result.
+pub fn verify_all_modules_for_kernels(
+    target: &ModuleTarget,
+    config: &Config,
+    kernel_versions: &[String],
+) -> Result<()> {
+    let outcomes = verify_all_modules_for_kernels_detailed(target, config, kernel_versions)?;
+    verify_outcomes_to_result(target, &outcomes)
+}
+
+/// Verify every module signature in `target` across `kernel_versions`,
+/// returning a per-kernel [`KernelVerifyOutcome`] instead of collapsing
+/// straight to pass/fail - this is what a `--format json` caller
+/// serializes. Reports progress through
+/// [`crate::utils::output::print_progress`].
+pub fn verify_all_modules_for_kernels_detailed(
+    target: &ModuleTarget,
+    config: &Config,
+    kernel_versions: &[String],
+) -> Result<Vec<KernelVerifyOutcome>> {
+    log::info!("Verifying {} module signatures...", target.display_name);
+
+    let mut outcomes = Vec::new();
+
+    for (index, kernel_version) in kernel_versions.iter().enumerate() {
+        crate::utils::output::print_progress(
+            index + 1,
+            kernel_versions.len(),
+            &format!("Verifying {} modules for kernel {}", target.display_name, kernel_version),
+        );
+
+        let outcome = match verify_modules_for_one_kernel(target, config, kernel_version) {
+            Ok((trusted, untrusted)) => KernelVerifyOutcome {
+                kernel_version: kernel_version.clone(),
+                trusted,
+                untrusted,
+                error: None,
+            },
+            Err(e) => KernelVerifyOutcome {
+                kernel_version: kernel_version.clone(),
+                trusted: 0,
+                untrusted: 0,
+                error: Some(e.to_string()),
+            },
+        };
+
+        if outcome.is_ok() {
+            log::info!("Kernel {}: {} module(s) trusted", kernel_version, outcome.trusted);
+        } else {
+            log::error!(
+                "Kernel {}: {} trusted, {} not trusted{}",
+                kernel_version,
+                outcome.trusted,
+                outcome.untrusted,
+                outcome
+                    .error
+                    .as_ref()
+                    .map(|e| format!(" ({})", e))
+                    .unwrap_or_default()
+            );
         }
-        None => {}
+
+        outcomes.push(outcome);
     }
-    
-    Ok(decompressed_path)
+
+    Ok(outcomes)
 }
 
-/// Verify all VirtualBox module signatures
-pub fn verify_all_modules() -> Result<()> {
-    log::info!("Verifying VirtualBox module signatures...");
-    
-    let modules = find_vbox_modules()?;
-    
-    let mut verified_count = 0;
-    let mut unverified_count = 0;
-    
+/// Reduces a set of [`KernelVerifyOutcome`]s to a single pass/fail [`Result`]
+pub fn verify_outcomes_to_result(target: &ModuleTarget, outcomes: &[KernelVerifyOutcome]) -> Result<()> {
+    let failed_kernels: Vec<&str> = outcomes
+        .iter()
+        .filter(|o| !o.is_ok())
+        .map(|o| o.kernel_version.as_str())
+        .collect();
+
+    if failed_kernels.is_empty() {
+        log::info!("All modules are properly signed across {} kernel(s)!", outcomes.len());
+        Ok(())
+    } else {
+        Err(VBoxError::SignatureVerificationFailed(format!(
+            "{} module(s) are not signed by our MOK for kernel(s): {}",
+            target.display_name,
+            failed_kernels.join(", ")
+        )))
+    }
+}
+
+/// Verifies one kernel's worth of `target`'s modules, returning `(trusted, untrusted)`
+fn verify_modules_for_one_kernel(
+    target: &ModuleTarget,
+    config: &Config,
+    kernel_version: &str,
+) -> Result<(usize, usize)> {
+    let modules = find_modules_for_kernel(target, kernel_version, config)?;
+
+    let mut trusted_count = 0;
+    let mut untrusted_count = 0;
+
     for module in &modules {
-        match verify_module_signature(module) {
-            Ok(true) => {
-                log::info!("Module is signed: {}", module.name);
-                verified_count += 1;
+        match inspect_module_signature(module, config) {
+            Ok(details) if details.status == SignatureStatus::Signed => {
+                log::info!(
+                    "Module is signed by our MOK: {} ({})",
+                    module.name,
+                    details.hash_algo.unwrap_or("unknown")
+                );
+                trusted_count += 1;
             }
-            Ok(false) => {
-                log::error!("Module is NOT signed: {}", module.name);
-                unverified_count += 1;
+            Ok(details) => {
+                log::error!(
+                    "Module {} is {} (hash: {}, signer: {})",
+                    module.name,
+                    details.status,
+                    details.hash_algo.unwrap_or("unknown"),
+                    details.signer_skid.as_deref().unwrap_or("none")
+                );
+                untrusted_count += 1;
             }
             Err(e) => {
                 log::error!("Failed to verify {}: {}", module.name, e);
-                unverified_count += 1;
+                untrusted_count += 1;
             }
         }
     }
-    
-    log::info!(
-        "Verification complete: {} signed, {} unsigned",
-        verified_count,
-        unverified_count
-    );
-    
-    if unverified_count > 0 {
-        Err(VBoxError::SignatureVerificationFailed(format!(
-            "{} module(s) are not signed",
-            unverified_count
-        )))
-    } else {
-        log::info!("All modules are properly signed!");
-        Ok(())
-    }
+
+    Ok((trusted_count, untrusted_count))
 }
 
-/// Load VirtualBox kernel modules
-pub fn load_vbox_modules() -> Result<()> {
+/// Load every module in `target`, in its configured order
+pub fn load_modules(target: &ModuleTarget) -> Result<()> {
     system::check_root()?;
-    log::info!("Loading VirtualBox kernel modules...");
-    
-    let modules = vec!["vboxdrv", "vboxnetflt", "vboxnetadp"];
-    
-    for module in modules {
+    log::info!("Loading {} kernel modules...", target.display_name);
+
+    for module in &target.modules {
         match system::load_module(module) {
             Ok(_) => log::info!("Loaded module: {}", module),
             Err(e) => {
@@ -118,38 +495,34 @@ pub fn load_vbox_modules() -> Result<()> {
             }
         }
     }
-    
-    log::info!("All VirtualBox modules loaded successfully");
+
+    log::info!("All {} modules loaded successfully", target.display_name);
     Ok(())
 }
 
-/// Unload VirtualBox kernel modules
-pub fn unload_vbox_modules() -> Result<()> {
+/// Unload every module in `target`, in reverse of its configured order
+pub fn unload_modules(target: &ModuleTarget) -> Result<()> {
     system::check_root()?;
-    log::info!("Unloading VirtualBox kernel modules...");
-    
-    // Unload in reverse order
-    let modules = vec!["vboxnetadp", "vboxnetflt", "vboxdrv"];
-    
-    for module in modules {
+    log::info!("Unloading {} kernel modules...", target.display_name);
+
+    for module in target.modules.iter().rev() {
         system::unload_module(module)?;
     }
-    
-    log::info!("All VirtualBox modules unloaded");
+
+    log::info!("All {} modules unloaded", target.display_name);
     Ok(())
 }
 
-/// Check if VirtualBox modules are loaded
-pub fn check_modules_loaded() -> Result<Vec<String>> {
-    let modules = vec!["vboxdrv", "vboxnetflt", "vboxnetadp"];
+/// Check which of `target`'s modules are loaded
+pub fn check_modules_loaded(target: &ModuleTarget) -> Result<Vec<String>> {
     let mut loaded = Vec::new();
-    
-    for module in modules {
+
+    for module in &target.modules {
         if system::is_module_loaded(module)? {
-            loaded.push(module.to_string());
+            loaded.push(module.clone());
         }
     }
-    
+
     Ok(loaded)
 }
 
@@ -162,11 +535,76 @@ pub fn get_module_info(module_name: &str) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_check_modules_loaded() {
         // This test should work on any system
-        let result = check_modules_loaded();
+        let config = Config::default();
+        let result = check_modules_loaded(&ModuleTarget::vbox(&config));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_signature_status_is_trusted() {
+        assert!(SignatureStatus::Signed.is_trusted());
+        assert!(!SignatureStatus::SignedByOtherKey.is_trusted());
+        assert!(!SignatureStatus::Unsigned.is_trusted());
+    }
+
+    #[test]
+    fn test_extract_skid_from_pem_or_text() {
+        let text = "X509v3 Subject Key Identifier: \n    AB:CD:EF:01\n";
+        assert_eq!(extract_skid_from_pem_or_text(text).unwrap(), "AB:CD:EF:01");
+    }
+
+    /// Real kernel module signatures are a KeyIdentifier-only SignerInfo
+    /// with no embedded cert, so `extract_signer_skid` falls back to
+    /// `openssl cms -cmsout -noout -print`, which never says "Subject Key
+    /// Identifier" as text - it prints `d.subjectKeyIdentifier:` followed by
+    /// a hex dump. This fragment is copied verbatim from a real `openssl
+    /// cms -sign -keyid -nocerts` SignerInfo print.
+    #[test]
+    fn test_extract_skid_from_cms_print_output() {
+        let text = "\
+        version: 3
+        d.subjectKeyIdentifier:
+          0000 - aa f6 10 29 cf c9 b4 65-dd 43 2f b6 15 a2 d5   ...)...e.C/....
+          000f - 5a 48 e4 f1 0b                                 ZH...
+        digestAlgorithm:
+          algorithm: sha256 (2.16.840.1.101.3.4.2.1)
+";
+        assert_eq!(
+            extract_skid_from_pem_or_text(text).unwrap(),
+            "AA:F6:10:29:CF:C9:B4:65:DD:43:2F:B6:15:A2:D5:5A:48:E4:F1:0B"
+        );
+    }
+
+    /// `extract_signer_skid`'s embedded-cert branch runs `openssl pkcs7
+    /// -print_certs -text`, not plain `-print_certs` (which never prints
+    /// "Subject Key Identifier" at all). This fragment is copied verbatim
+    /// from a real `openssl x509 -text -noout` certificate dump - the same
+    /// shape `pkcs7 -print_certs -text` produces per embedded certificate.
+    #[test]
+    fn test_extract_skid_from_pkcs7_print_certs_text_output() {
+        let text = "\
+        X509v3 extensions:
+            X509v3 Basic Constraints:
+                CA:FALSE
+            X509v3 Subject Key Identifier:
+                AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01
+            X509v3 Authority Key Identifier:
+                keyid:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01
+";
+        assert_eq!(
+            extract_skid_from_pem_or_text(text).unwrap(),
+            "AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01"
+        );
+    }
+
+    #[test]
+    fn test_hash_algo_name() {
+        assert_eq!(hash_algo_name(4), "sha256");
+        assert_eq!(hash_algo_name(6), "sha512");
+        assert_eq!(hash_algo_name(200), "unknown");
+    }
 }
@@ -0,0 +1,180 @@
+//! Persistent, rotated operation log.
+//!
+//! This is distinct from [`crate::utils::logger::VBoxLogger`], which mirrors
+//! `log::` line output for the running process. `OpLog` instead captures the
+//! full stdout/stderr of the DKMS rebuild and `sign-file` invocations - the
+//! output a user actually needs when a kernel-update recovery goes wrong -
+//! into a rotated `/var/log/vbox-sb-manager.log`, modeled on the
+//! `setup_log()` rotation in upstream's `vboxdrv.sh`.
+
+use crate::error::Result;
+use chrono::Local;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Mutex, Once};
+
+/// Default location for the operation log
+pub const DEFAULT_LOG_PATH: &str = "/var/log/vbox-sb-manager.log";
+
+/// Number of rotated generations to retain (`.1` through `.4`)
+const MAX_GENERATIONS: u32 = 4;
+
+/// Rotated operation log that captures full command output for post-mortem
+/// debugging of a failed rebuild/sign run.
+pub struct OpLog {
+    file: Mutex<std::fs::File>,
+}
+
+impl OpLog {
+    /// Rotates any existing log generations (once per process - a `Full`
+    /// run opens an `OpLog` for each of its sub-steps and must not rotate
+    /// its own banner away) and opens the log file for appending.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        static ROTATE_ONCE: Once = Once::new();
+        let path = path.as_ref();
+
+        let mut rotate_err = None;
+        ROTATE_ONCE.call_once(|| {
+            rotate_err = rotate(path).err();
+        });
+        if let Some(e) = rotate_err {
+            return Err(e);
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Writes a banner line delimiting a logical section of the run, e.g.
+    /// "Step 1/4: Rebuilding modules".
+    pub fn banner(&self, title: &str) {
+        self.write_line(&format!("===== {} =====", title));
+    }
+
+    /// Appends a single timestamped line
+    pub fn write_line(&self, line: &str) {
+        if let Ok(mut file) = self.file.lock() {
+            let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+            let _ = writeln!(file, "[{}] {}", timestamp, line);
+        }
+    }
+
+    /// Runs a command, teeing its full stdout/stderr into the log file even
+    /// on success, and returns the captured `Output` for the caller's normal
+    /// `execute_command_checked`-style error handling.
+    pub fn run_and_capture(&self, cmd: &str, args: &[&str]) -> Result<Output> {
+        self.write_line(&format!("$ {} {}", cmd, args.join(" ")));
+
+        let output = Command::new(cmd).args(args).output()?;
+
+        if !output.stdout.is_empty() {
+            self.write_raw(&output.stdout);
+        }
+        if !output.stderr.is_empty() {
+            self.write_raw(&output.stderr);
+        }
+        self.write_line(&format!("(exit status: {})", output.status));
+
+        Ok(output)
+    }
+
+    fn write_raw(&self, bytes: &[u8]) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(bytes);
+            if !bytes.ends_with(b"\n") {
+                let _ = file.write_all(b"\n");
+            }
+        }
+    }
+}
+
+/// Shift-rotates `path` -> `path.1` -> ... -> `path.4`, dropping the oldest
+/// generation, mirroring `vboxdrv.sh`'s `.log` -> `.log.1` -> ... cascade.
+fn rotate(path: &Path) -> Result<()> {
+    let oldest = generation_path(path, MAX_GENERATIONS);
+    if oldest.exists() {
+        std::fs::remove_file(&oldest)?;
+    }
+
+    let mut generation = MAX_GENERATIONS;
+    while generation > 1 {
+        let from = generation_path(path, generation - 1);
+        let to = generation_path(path, generation);
+        if from.exists() {
+            std::fs::rename(&from, &to)?;
+        }
+        generation -= 1;
+    }
+
+    if path.exists() {
+        std::fs::rename(path, generation_path(path, 1))?;
+    }
+
+    Ok(())
+}
+
+fn generation_path(path: &Path, generation: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", generation));
+    PathBuf::from(name)
+}
+
+/// Opens the default operation log if running as root, logging (but not
+/// failing the caller) on error so a missing `/var/log` mount doesn't take
+/// down the whole command.
+pub fn open_if_root() -> Option<OpLog> {
+    if !nix::unistd::Uid::effective().is_root() {
+        return None;
+    }
+
+    match OpLog::open(DEFAULT_LOG_PATH) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            log::warn!("Failed to open operation log {}: {}", DEFAULT_LOG_PATH, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotation_cascade() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("test.log");
+
+        for i in 0..6 {
+            std::fs::write(&log_path, format!("run {}", i)).unwrap();
+            rotate(&log_path).unwrap();
+            // rotate() renames the just-written file away, so recreate it
+            // the way OpLog::open would via OpenOptions.
+            std::fs::write(&log_path, "").unwrap();
+        }
+
+        assert!(generation_path(&log_path, 1).exists());
+        assert!(generation_path(&log_path, 4).exists());
+        assert!(!generation_path(&log_path, 5).exists());
+    }
+
+    #[test]
+    fn test_run_and_capture() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("test.log");
+        let log = OpLog::open(&log_path).unwrap();
+
+        let output = log.run_and_capture("echo", &["hello"]).unwrap();
+        assert!(output.status.success());
+
+        let contents = std::fs::read_to_string(&log_path).unwrap();
+        assert!(contents.contains("echo hello"));
+        assert!(contents.contains("hello"));
+    }
+}
@@ -6,38 +6,79 @@ use colored::*;
 use log::{Level, LevelFilter, Log, Metadata, Record};
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
-/// Custom logger that writes to both console and file
+/// State behind the mutex: the open file handle and its current size,
+/// cached so [`VBoxLogger::log`] can decide whether to rotate without
+/// `fstat`-ing on every single line.
+struct LogFileState {
+    file: std::fs::File,
+    size: u64,
+}
+
+/// Custom logger that writes to both console and file. Rotates the file
+/// through `.1`..`.max_generations` once it would grow past `max_bytes`,
+/// the same cascade `vboxdrv.sh`'s `setup_log()` uses for
+/// `/var/log/vbox-setup.log`.
 pub struct VBoxLogger {
-    log_file: Mutex<std::fs::File>,
+    log_file_path: PathBuf,
+    state: Mutex<LogFileState>,
     console_enabled: bool,
+    max_bytes: u64,
+    max_generations: u32,
 }
 
 impl VBoxLogger {
-    /// Creates a new logger
-    pub fn new<P: AsRef<Path>>(log_file_path: P, console_enabled: bool) -> Result<Self> {
-        let log_file = OpenOptions::new()
+    /// Creates a new logger, rotating `log_file_path` first if it's already
+    /// past `max_bytes`
+    pub fn new<P: AsRef<Path>>(
+        log_file_path: P,
+        console_enabled: bool,
+        max_bytes: u64,
+        max_generations: u32,
+    ) -> Result<Self> {
+        let log_file_path = log_file_path.as_ref().to_path_buf();
+
+        if max_bytes > 0 && std::fs::metadata(&log_file_path).map(|m| m.len()).unwrap_or(0) > max_bytes {
+            rotate(&log_file_path, max_generations);
+        }
+
+        let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(log_file_path)?;
-        
+            .open(&log_file_path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+
         Ok(Self {
-            log_file: Mutex::new(log_file),
+            log_file_path,
+            state: Mutex::new(LogFileState { file, size }),
             console_enabled,
+            max_bytes,
+            max_generations,
         })
     }
-    
+
     /// Initializes the global logger
     pub fn init<P: AsRef<Path>>(log_file_path: P, level: LevelFilter) -> Result<()> {
-        let logger = Box::new(Self::new(log_file_path, true)?);
+        Self::init_with_rotation(log_file_path, level, 10 * 1024 * 1024, 4)
+    }
+
+    /// Same as [`Self::init`], but with an explicit rotation policy (see
+    /// `config.log_max_bytes`/`config.log_max_generations`)
+    pub fn init_with_rotation<P: AsRef<Path>>(
+        log_file_path: P,
+        level: LevelFilter,
+        max_bytes: u64,
+        max_generations: u32,
+    ) -> Result<()> {
+        let logger = Box::new(Self::new(log_file_path, true, max_bytes, max_generations)?);
         log::set_boxed_logger(logger)
             .map_err(|e| crate::error::VBoxError::Other(format!("Failed to set logger: {}", e)))?;
         log::set_max_level(level);
         Ok(())
     }
-    
+
     fn format_console_message(&self, record: &Record) -> String {
         let level_str = match record.level() {
             Level::Error => "[ERROR]".red().bold(),
@@ -60,28 +101,71 @@ impl Log for VBoxLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         metadata.level() <= Level::Trace
     }
-    
+
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            // Write to file
-            if let Ok(mut file) = self.log_file.lock() {
-                let _ = writeln!(file, "{}", self.format_file_message(record));
+            // Write to file, rotating first if this line would push us past
+            // max_bytes. `state.size` is tracked incrementally so this never
+            // needs to `fstat` the file.
+            if let Ok(mut state) = self.state.lock() {
+                let line = self.format_file_message(record);
+                let line_len = line.len() as u64 + 1; // + the trailing newline
+
+                if self.max_bytes > 0 && state.size + line_len > self.max_bytes {
+                    rotate(&self.log_file_path, self.max_generations);
+                    match OpenOptions::new().create(true).append(true).open(&self.log_file_path) {
+                        Ok(file) => {
+                            state.file = file;
+                            state.size = 0;
+                        }
+                        Err(_) => return,
+                    }
+                }
+
+                if writeln!(state.file, "{}", line).is_ok() {
+                    state.size += line_len;
+                }
             }
-            
+
             // Write to console
             if self.console_enabled {
                 eprintln!("{}", self.format_console_message(record));
             }
         }
     }
-    
+
     fn flush(&self) {
-        if let Ok(mut file) = self.log_file.lock() {
-            let _ = file.flush();
+        if let Ok(mut state) = self.state.lock() {
+            let _ = state.file.flush();
         }
     }
 }
 
+/// Cascades `log_file_path` through `.1`..`.max_generations`: deletes the
+/// oldest generation, shifts every other generation up by one, then moves
+/// the live file to `.1`. Best-effort - a missing generation file is not an
+/// error, since not every slot will be populated yet on a fresh install.
+fn rotate(log_file_path: &Path, max_generations: u32) {
+    if max_generations == 0 {
+        let _ = std::fs::remove_file(log_file_path);
+        return;
+    }
+
+    let generation_path = |n: u32| {
+        let mut path = log_file_path.as_os_str().to_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    };
+
+    let _ = std::fs::remove_file(generation_path(max_generations));
+
+    for generation in (1..max_generations).rev() {
+        let _ = std::fs::rename(generation_path(generation), generation_path(generation + 1));
+    }
+
+    let _ = std::fs::rename(log_file_path, generation_path(1));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,7 +174,41 @@ mod tests {
     #[test]
     fn test_logger_creation() {
         let temp_file = NamedTempFile::new().unwrap();
-        let logger = VBoxLogger::new(temp_file.path(), true);
+        let logger = VBoxLogger::new(temp_file.path(), true, 10 * 1024 * 1024, 4);
         assert!(logger.is_ok());
     }
+
+    #[test]
+    fn test_rotate_cascades_generations_and_drops_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("test.log");
+
+        std::fs::write(&log_path, "current").unwrap();
+        std::fs::write(format!("{}.1", log_path.display()), "gen1").unwrap();
+        std::fs::write(format!("{}.3", log_path.display()), "gen3").unwrap();
+
+        rotate(&log_path, 4);
+
+        assert!(!log_path.exists());
+        assert_eq!(std::fs::read_to_string(format!("{}.1", log_path.display())).unwrap(), "current");
+        assert_eq!(std::fs::read_to_string(format!("{}.2", log_path.display())).unwrap(), "gen1");
+        assert_eq!(std::fs::read_to_string(format!("{}.4", log_path.display())).unwrap(), "gen3");
+    }
+
+    #[test]
+    fn test_logger_rotates_when_over_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("rotating.log");
+        std::fs::write(&log_path, "0123456789").unwrap();
+
+        // A fresh logger over an 8-byte budget should rotate the existing
+        // file out of the way before opening a clean one.
+        let _logger = VBoxLogger::new(&log_path, false, 8, 4).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "");
+        assert_eq!(
+            std::fs::read_to_string(format!("{}.1", log_path.display())).unwrap(),
+            "0123456789"
+        );
+    }
 }
@@ -113,7 +113,7 @@ pub fn is_secure_boot_enabled() -> Result<bool> {
 
 /// Check required dependencies
 pub fn check_dependencies() -> Result<Vec<String>> {
-    let deps = vec!["openssl", "mokutil", "modinfo", "modprobe", "zstd"];
+    let deps = vec!["openssl", "mokutil", "modinfo", "modprobe"];
     let mut missing = Vec::new();
     
     for dep in deps {